@@ -1,7 +1,17 @@
 use serde::{Deserialize, Deserializer};
-use serde_json;
+use std::fmt;
 use std::str::FromStr;
 
+mod live;
+mod logging;
+mod output_root;
+mod traces;
+
+pub use live::{LiveEvent, LiveEventType, LIVE_EVENTS_CHANNEL};
+pub use logging::{init_tracing, LogFormat};
+pub use output_root::{OutputRootPreimage, OUTPUT_VERSION_V0};
+pub use traces::{set_remote_parent, trace_context_headers, trace_id, TracingGuard};
+
 /// Custom deserializer that lowercases address strings
 fn deserialize_address_lowercase<'de, D>(deserializer: D) -> Result<String, D::Error>
 where
@@ -33,16 +43,17 @@ pub enum ChainName {
     WorldChain,
 }
 
-impl ToString for ChainName {
-    fn to_string(&self) -> String {
-        match self {
-            ChainName::Arbitrum => "arbitrum".to_string(),
-            ChainName::ApeChain => "ape_chain".to_string(),
-            ChainName::Base => "base".to_string(),
-            ChainName::Optimism => "optimism".to_string(),
-            ChainName::Zora => "zora".to_string(),
-            ChainName::WorldChain => "world_chain".to_string(),
-        }
+impl fmt::Display for ChainName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ChainName::Arbitrum => "arbitrum",
+            ChainName::ApeChain => "ape_chain",
+            ChainName::Base => "base",
+            ChainName::Optimism => "optimism",
+            ChainName::Zora => "zora",
+            ChainName::WorldChain => "world_chain",
+        };
+        f.write_str(name)
     }
 }
 
@@ -70,13 +81,14 @@ pub enum ChainType {
     Sepolia,
 }
 
-impl ToString for ChainType {
-    fn to_string(&self) -> String {
-        match self {
-            ChainType::Mainnet => "mainnet".to_string(),
-            ChainType::Goerli => "goerli".to_string(),
-            ChainType::Sepolia => "sepolia".to_string(),
-        }
+impl fmt::Display for ChainType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ChainType::Mainnet => "mainnet",
+            ChainType::Goerli => "goerli",
+            ChainType::Sepolia => "sepolia",
+        };
+        f.write_str(name)
     }
 }
 
@@ -98,13 +110,9 @@ pub struct Network {
     pub chain_type: ChainType,
 }
 
-impl ToString for Network {
-    fn to_string(&self) -> String {
-        format!(
-            "{}_{}",
-            self.chain_name.to_string(),
-            self.chain_type.to_string()
-        )
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}_{}", self.chain_name, self.chain_type)
     }
 }
 
@@ -112,9 +120,11 @@ impl FromStr for Network {
     type Err = eyre::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parts: Vec<&str> = s.rsplitn(2, '_').collect();
-        let chain_name = ChainName::from_str(parts[1])?;
-        let chain_type = ChainType::from_str(parts[0])?;
+        let (chain_name, chain_type) = s
+            .rsplit_once('_')
+            .ok_or_else(|| eyre::eyre!("Invalid network: {}", s))?;
+        let chain_name = ChainName::from_str(chain_name)?;
+        let chain_type = ChainType::from_str(chain_type)?;
         Ok(Network {
             chain_name,
             chain_type,
@@ -122,6 +132,35 @@ impl FromStr for Network {
     }
 }
 
+/// The L1 block tag the indexer is allowed to index up to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum L1Finality {
+    /// The latest block minus `block_delay`
+    #[default]
+    Latest,
+    /// The block tagged `safe` by the L1 node
+    Safe,
+    /// The block tagged `finalized` by the L1 node
+    Finalized,
+}
+
+/// The anomalies the indexer of a network raises alerts for, none by default
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AlertRules {
+    /// An OutputProposed whose l2_output_index is not the previous index plus one
+    pub output_index_gap: bool,
+    /// A dispute game created by another address than `trusted_proposer_address`
+    pub untrusted_proposer: bool,
+    /// A dispute game resolved as CHALLENGER_WINS
+    pub challenger_wins: bool,
+    /// An output root or root claim that does not match its L2 block
+    pub root_claim_mismatch: bool,
+    /// Seconds a stream may fall behind the L1 head before an alert is raised
+    pub max_lag_sec: Option<u64>,
+}
+
 /// A struct that represents the Networks struct in the JSON file
 #[derive(Debug, Deserialize)]
 pub struct NetworkConfig {
@@ -130,6 +169,8 @@ pub struct NetworkConfig {
     pub l1_contract: String,
     pub l1_contract_deployment_block: u64,
     pub block_delay: u64,
+    #[serde(default)]
+    pub l1_finality: L1Finality,
     pub poll_period_sec: u64,
     #[serde(default)]
     pub batch_size: Option<u64>,
@@ -143,15 +184,52 @@ pub struct NetworkConfig {
     pub transition_to_dispute_game_system_l2_block: Option<u64>,
     #[serde(default, deserialize_with = "deserialize_optional_address_lowercase")]
     pub trusted_proposer_address: Option<String>,
+    /// Minimum seconds between two indexing iterations when following newHeads over WebSocket
+    #[serde(default)]
+    pub min_head_interval_sec: Option<u64>,
+    /// The first L2 block produced at a fixed block time, e.g. the Bedrock block of OP mainnet
+    #[serde(default)]
+    pub l2_genesis_block: Option<u64>,
+    /// The L2 timestamp of `l2_genesis_block`
+    #[serde(default)]
+    pub l2_genesis_timestamp: Option<u64>,
+    /// Seconds between two L2 blocks
+    #[serde(default)]
+    pub l2_block_time_sec: Option<u64>,
+    /// Seconds between two status checks of the in progress dispute games, 600 by default
+    #[serde(default)]
+    pub game_refresh_period_sec: Option<u64>,
+    #[serde(default)]
+    pub alerts: AlertRules,
+}
+
+impl NetworkConfig {
+    /// A function that maps an L2 timestamp to the last L2 block produced at or before it.
+    ///
+    /// Returns:
+    /// * The L2 block number, or None if the network has no fixed block time configured
+    ///   or the timestamp is before `l2_genesis_timestamp`
+    pub fn l2_block_at_timestamp(&self, l2_timestamp: u64) -> Option<u64> {
+        let genesis_timestamp = self.l2_genesis_timestamp?;
+        let block_time = self.l2_block_time_sec.filter(|time| *time > 0)?;
+        let elapsed = l2_timestamp.checked_sub(genesis_timestamp)?;
+        Some(self.l2_genesis_block.unwrap_or(0) + elapsed / block_time)
+    }
 }
 
 /// A builder that gets config from embedded JSON and returns NetworkConfig.
+///
 /// Parameters:
 /// * chain_type: The chain type (mainnet, sepolia, goerli)
 /// * chain_name: The chain name (arbitrum, optimism, base, etc.)
+///
 /// Returns:
-/// * NetworkConfig struct that contains all the network config data
-pub fn get_network_config(chain_type: ChainType, chain_name: ChainName) -> NetworkConfig {
+/// * NetworkConfig struct that contains all the network config data, or an error if the
+///   network is not supported or its embedded config is invalid
+pub fn get_network_config(
+    chain_type: ChainType,
+    chain_name: ChainName,
+) -> Result<NetworkConfig, eyre::Error> {
     let network = Network {
         chain_name,
         chain_type,
@@ -173,10 +251,11 @@ pub fn get_network_config(chain_type: ChainType, chain_name: ChainName) -> Netwo
         }
         "zora_mainnet" => include_str!("../../monitor_events/networks/zora_mainnet.json"),
         "zora_sepolia" => include_str!("../../monitor_events/networks/zora_sepolia.json"),
-        _ => panic!("Unsupported network: {}", network.to_string()),
+        _ => return Err(eyre::eyre!("Unsupported network: {}", network)),
     };
 
-    serde_json::from_str(config_json).unwrap()
+    serde_json::from_str(config_json)
+        .map_err(|e| eyre::eyre!("Invalid network config for {}: {}", network, e))
 }
 
 pub fn create_network_from_strings(
@@ -190,3 +269,51 @@ pub fn create_network_from_strings(
         chain_type,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn l2_block_at_timestamp_counts_blocks_from_genesis() {
+        let config = get_network_config(ChainType::Mainnet, ChainName::Optimism).unwrap();
+        let genesis_block = config.l2_genesis_block.unwrap();
+        let genesis_timestamp = config.l2_genesis_timestamp.unwrap();
+
+        assert_eq!(
+            config.l2_block_at_timestamp(genesis_timestamp),
+            Some(genesis_block)
+        );
+        // A timestamp between two blocks resolves to the earlier one
+        assert_eq!(
+            config.l2_block_at_timestamp(genesis_timestamp + 1),
+            Some(genesis_block)
+        );
+        assert_eq!(
+            config.l2_block_at_timestamp(genesis_timestamp + 2),
+            Some(genesis_block + 1)
+        );
+        assert_eq!(
+            config.l2_block_at_timestamp(genesis_timestamp + 3600),
+            Some(genesis_block + 1800)
+        );
+    }
+
+    #[test]
+    fn l2_block_at_timestamp_rejects_timestamps_before_genesis() {
+        let config = get_network_config(ChainType::Mainnet, ChainName::Optimism).unwrap();
+        let genesis_timestamp = config.l2_genesis_timestamp.unwrap();
+        assert_eq!(config.l2_block_at_timestamp(genesis_timestamp - 1), None);
+        assert_eq!(config.l2_block_at_timestamp(0), None);
+    }
+
+    #[test]
+    fn l2_block_at_timestamp_needs_a_block_time() {
+        let mut config = get_network_config(ChainType::Mainnet, ChainName::Arbitrum).unwrap();
+        assert_eq!(config.l2_block_at_timestamp(u64::MAX), None);
+
+        config.l2_genesis_timestamp = Some(100);
+        config.l2_block_time_sec = Some(0);
+        assert_eq!(config.l2_block_at_timestamp(200), None);
+    }
+}
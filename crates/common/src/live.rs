@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::str::FromStr;
+
+/// The Postgres channel the indexer publishes the live events on, listened to by the
+/// micro-service to stream them
+pub const LIVE_EVENTS_CHANNEL: &str = "l2_indexer_events";
+
+/// The kinds of stored changes that are streamed live
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum LiveEventType {
+    /// An output root was proposed on L1, to the L2OutputOracle or the Arbitrum rollup
+    OutputProposed,
+    /// A dispute game was created by the factory
+    DisputeGameCreated,
+    /// An in progress dispute game resolved, see `game_state`
+    DisputeGameResolved,
+}
+
+impl LiveEventType {
+    pub const ALL: [LiveEventType; 3] = [
+        LiveEventType::OutputProposed,
+        LiveEventType::DisputeGameCreated,
+        LiveEventType::DisputeGameResolved,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LiveEventType::OutputProposed => "output_proposed",
+            LiveEventType::DisputeGameCreated => "dispute_game_created",
+            LiveEventType::DisputeGameResolved => "dispute_game_resolved",
+        }
+    }
+}
+
+impl FromStr for LiveEventType {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LiveEventType::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == s)
+            .ok_or_else(|| eyre::eyre!("unknown event type {}", s))
+    }
+}
+
+/// A change stored by the indexer, as published on `LIVE_EVENTS_CHANNEL`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct LiveEvent {
+    /// `<network>:<type>:<l2_block_number or game_index>`, the same if the indexer
+    /// publishes the event again
+    pub id: String,
+    pub network: String,
+    #[serde(rename = "type")]
+    pub kind: LiveEventType,
+    /// The fields of the output or of the game
+    #[cfg_attr(feature = "openapi", schema(value_type = Object))]
+    pub data: Value,
+}
+
+impl LiveEvent {
+    /// A function that builds an event, its id is derived from the network, the kind and
+    /// the key.
+    /// Parameters:
+    /// * network: The network of the change, e.g. `optimism_mainnet`
+    /// * kind: The kind of change
+    /// * key: The L2 block number of an output or the index of a game
+    /// * data: The fields of the output or of the game
+    pub fn new(network: &str, kind: LiveEventType, key: u64, data: Value) -> Self {
+        Self {
+            id: format!("{}:{}:{}", network, kind.as_str(), key),
+            network: network.to_string(),
+            kind,
+            data,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn live_events_round_trip_with_their_type() {
+        let event = LiveEvent::new(
+            "optimism_mainnet",
+            LiveEventType::DisputeGameResolved,
+            42,
+            serde_json::json!({ "game_index": 42 }),
+        );
+        assert_eq!(event.id, "optimism_mainnet:dispute_game_resolved:42");
+        let payload = serde_json::to_value(&event).unwrap();
+        assert_eq!(payload["type"], "dispute_game_resolved");
+
+        let parsed: LiveEvent = serde_json::from_value(payload).unwrap();
+        assert_eq!(parsed.kind, LiveEventType::DisputeGameResolved);
+        assert_eq!(parsed.data["game_index"], 42);
+    }
+
+    #[test]
+    fn live_event_types_parse_from_their_names() {
+        for kind in LiveEventType::ALL {
+            assert_eq!(kind.as_str().parse::<LiveEventType>().unwrap(), kind);
+        }
+        assert!("output_finalized".parse::<LiveEventType>().is_err());
+    }
+}
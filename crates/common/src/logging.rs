@@ -0,0 +1,76 @@
+use std::str::FromStr;
+
+use eyre::{eyre, Result};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter, Layer, Registry};
+
+use crate::traces::{init_tracer, TracingGuard};
+
+/// The format of the log lines, set with `LOG_FORMAT`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum LogFormat {
+    /// Human readable lines, for local runs
+    #[default]
+    Text,
+    /// One JSON object per line carrying the fields of the event and of its spans,
+    /// for log shippers
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(eyre!("Invalid LOG_FORMAT {}, expected text or json", s)),
+        }
+    }
+}
+
+/// A function that installs the global tracing subscriber of a binary.
+/// The level is read from `RUST_LOG`, e.g. `info,monitor_events=debug`, and the format
+/// from `LOG_FORMAT` (`text` if unset). Spans are also exported to OpenTelemetry when
+/// `OTEL_EXPORTER_OTLP_ENDPOINT` or `OTEL_TRACES_FILE` is set.
+/// Parameters:
+/// * service_name: The `service.name` of the exported spans
+/// * default_filter: The filter used when `RUST_LOG` is not set
+///
+/// Returns:
+/// * The guard flushing the exported spans on drop
+pub fn init_tracing(service_name: &'static str, default_filter: &str) -> Result<TracingGuard> {
+    let format = match std::env::var("LOG_FORMAT") {
+        Ok(format) if !format.is_empty() => format.parse()?,
+        _ => LogFormat::default(),
+    };
+    let filter = EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(default_filter))
+        .map_err(|e| eyre!("Invalid log filter: {}", e))?;
+
+    let fmt_layer: Box<dyn Layer<Registry> + Send + Sync> = match format {
+        LogFormat::Text => fmt::layer().with_target(false).boxed(),
+        LogFormat::Json => fmt::layer()
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .flatten_event(true)
+            .boxed(),
+    };
+    let (tracer, guard) = init_tracer(service_name)?;
+    let otel_layer: Option<Box<dyn Layer<Registry> + Send + Sync>> =
+        tracer.map(|tracer| tracing_opentelemetry::layer().with_tracer(tracer).boxed());
+
+    tracing_subscriber::registry()
+        .with(
+            vec![Some(fmt_layer), otel_layer]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>(),
+        )
+        .with(filter)
+        .try_init()
+        .map_err(|e| eyre!("Failed to install the tracing subscriber: {}", e))?;
+    Ok(guard)
+}
@@ -0,0 +1,65 @@
+use tiny_keccak::{Hasher, Keccak};
+
+/// The output version of the OP stack output root preimage, always 0 so far
+pub const OUTPUT_VERSION_V0: &str =
+    "0x0000000000000000000000000000000000000000000000000000000000000000";
+
+/// The preimage of an OP stack output root, as 0x prefixed hex strings:
+/// `output_root = keccak256(version ++ state_root ++ message_passer_storage_root ++ block_hash)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputRootPreimage {
+    pub version: String,
+    pub state_root: String,
+    /// The storage root of the L2ToL1MessagePasser, called withdrawal storage root by op-node
+    pub message_passer_storage_root: String,
+    pub block_hash: String,
+}
+
+impl OutputRootPreimage {
+    /// A function that builds a version 0 preimage
+    pub fn v0(state_root: String, message_passer_storage_root: String, block_hash: String) -> Self {
+        Self {
+            version: OUTPUT_VERSION_V0.to_string(),
+            state_root,
+            message_passer_storage_root,
+            block_hash,
+        }
+    }
+
+    /// A function that hashes the preimage.
+    ///
+    /// Returns:
+    /// * The output root as 0x prefixed lowercase hex, or an error if a word is not
+    ///   32 bytes of hex
+    pub fn output_root(&self) -> Result<String, eyre::Error> {
+        let mut hasher = Keccak::v256();
+        for (name, word) in [
+            ("version", &self.version),
+            ("state_root", &self.state_root),
+            (
+                "message_passer_storage_root",
+                &self.message_passer_storage_root,
+            ),
+            ("block_hash", &self.block_hash),
+        ] {
+            hasher.update(&decode_word(name, word)?);
+        }
+        let mut output_root = [0u8; 32];
+        hasher.finalize(&mut output_root);
+        Ok(format!("0x{}", hex::encode(output_root)))
+    }
+
+    /// A function that checks the preimage against an output root.
+    /// A preimage that cannot be hashed does not match anything.
+    pub fn verify(&self, output_root: &str) -> bool {
+        self.output_root()
+            .is_ok_and(|computed| computed.eq_ignore_ascii_case(output_root))
+    }
+}
+
+fn decode_word(name: &str, word: &str) -> Result<[u8; 32], eyre::Error> {
+    let bytes = hex::decode(word.trim_start_matches("0x"))
+        .map_err(|e| eyre::eyre!("invalid {} hex {}: {}", name, word, e))?;
+    <[u8; 32]>::try_from(bytes.as_slice())
+        .map_err(|_| eyre::eyre!("{} must be 32 bytes, got {}", name, word))
+}
@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use eyre::{eyre, Result, WrapErr};
+use opentelemetry::global;
+use opentelemetry::trace::{Status, TraceContextExt, TracerProvider};
+use opentelemetry::Value;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::error::{OTelSdkError, OTelSdkResult};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{SdkTracer, SdkTracerProvider, SpanData, SpanExporter};
+use opentelemetry_sdk::Resource;
+use serde_json::{json, Map};
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// Where the spans of a binary are exported, chosen from the environment
+enum TraceExport {
+    /// OTLP over HTTP/protobuf to `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT` or
+    /// `OTEL_EXPORTER_OTLP_ENDPOINT`, e.g. `http://localhost:4318`
+    Otlp,
+    /// One JSON object per span appended to `OTEL_TRACES_FILE`, for local testing
+    File(String),
+}
+
+impl TraceExport {
+    fn from_env() -> Option<Self> {
+        let var = |key: &str| std::env::var(key).ok().filter(|value| !value.is_empty());
+        if let Some(path) = var("OTEL_TRACES_FILE") {
+            return Some(TraceExport::File(path));
+        }
+        var("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT")
+            .or_else(|| var("OTEL_EXPORTER_OTLP_ENDPOINT"))
+            .map(|_| TraceExport::Otlp)
+    }
+}
+
+/// Flushes the pending spans when the binary exits, keep it alive until then
+#[must_use = "spans are only flushed when the guard is dropped"]
+pub struct TracingGuard {
+    provider: Option<SdkTracerProvider>,
+}
+
+impl Drop for TracingGuard {
+    fn drop(&mut self) {
+        if let Some(provider) = self.provider.take() {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("Failed to flush the pending spans: {e}");
+            }
+        }
+    }
+}
+
+/// A function that sets up the span export of a binary, if configured.
+/// W3C `traceparent` propagation is always installed.
+/// Parameters:
+/// * service_name: The `service.name` of the spans, overridden by `OTEL_SERVICE_NAME`
+///
+/// Returns:
+/// * The tracer to build the `tracing` layer from, and the guard flushing it
+pub(crate) fn init_tracer(service_name: &'static str) -> Result<(Option<SdkTracer>, TracingGuard)> {
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let Some(export) = TraceExport::from_env() else {
+        return Ok((None, TracingGuard { provider: None }));
+    };
+
+    let service_name = std::env::var("OTEL_SERVICE_NAME").unwrap_or(service_name.to_string());
+    let builder = SdkTracerProvider::builder()
+        .with_resource(Resource::builder().with_service_name(service_name).build());
+    let provider = match export {
+        TraceExport::Otlp => {
+            let exporter = opentelemetry_otlp::SpanExporter::builder()
+                .with_http()
+                .with_timeout(Duration::from_secs(10))
+                .build()
+                .wrap_err("Failed to build the OTLP exporter")?;
+            builder.with_batch_exporter(exporter).build()
+        }
+        TraceExport::File(path) => builder
+            .with_batch_exporter(FileExporter::open(&path)?)
+            .build(),
+    };
+
+    let tracer = provider.tracer("l2-indexer");
+    Ok((
+        Some(tracer),
+        TracingGuard {
+            provider: Some(provider),
+        },
+    ))
+}
+
+/// A function that returns the trace id of a span, if it is exported
+pub fn trace_id(span: &Span) -> Option<String> {
+    let context = span.context();
+    let span_context = context.span().span_context().clone();
+    span_context
+        .is_valid()
+        .then(|| span_context.trace_id().to_string())
+}
+
+/// A function that continues the trace of an incoming request from its W3C
+/// `traceparent` and `tracestate` headers.
+/// Parameters:
+/// * span: The span of the request
+/// * header: Returns the value of a request header
+pub fn set_remote_parent<'a>(span: &Span, header: impl Fn(&str) -> Option<&'a str>) {
+    let context = global::get_text_map_propagator(|propagator| {
+        let carrier: HashMap<String, String> = propagator
+            .fields()
+            .filter_map(|name| Some((name.to_string(), header(name)?.to_string())))
+            .collect();
+        propagator.extract(&carrier)
+    });
+    // Fails when spans are not exported, there is no trace to continue then
+    let _ = span.set_parent(context);
+}
+
+/// A function that returns the W3C trace context headers propagating a span to an
+/// outgoing request, empty when spans are not exported
+pub fn trace_context_headers(span: &Span) -> HashMap<String, String> {
+    let mut carrier = HashMap::new();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&span.context(), &mut carrier)
+    });
+    carrier
+}
+
+/// Writes finished spans as JSON lines, so that traces can be inspected without a collector
+struct FileExporter {
+    path: String,
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl fmt::Debug for FileExporter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FileExporter")
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+impl FileExporter {
+    fn open(path: &str) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .wrap_err_with(|| format!("Failed to open OTEL_TRACES_FILE {}", path))?;
+        Ok(Self {
+            path: path.to_string(),
+            writer: Mutex::new(BufWriter::new(file)),
+        })
+    }
+
+    fn write(&self, batch: Vec<SpanData>) -> Result<()> {
+        let mut writer = self
+            .writer
+            .lock()
+            .map_err(|_| eyre!("The trace file writer is poisoned"))?;
+        for span in batch {
+            serde_json::to_writer(&mut *writer, &span_to_json(&span))?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+impl SpanExporter for FileExporter {
+    async fn export(&self, batch: Vec<SpanData>) -> OTelSdkResult {
+        self.write(batch)
+            .map_err(|e| OTelSdkError::InternalFailure(e.to_string()))
+    }
+}
+
+fn unix_nanos(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+}
+
+fn span_to_json(span: &SpanData) -> serde_json::Value {
+    let attributes: Map<String, serde_json::Value> = span
+        .attributes
+        .iter()
+        .map(|attribute| {
+            let value = match &attribute.value {
+                Value::Bool(value) => json!(value),
+                Value::I64(value) => json!(value),
+                Value::F64(value) => json!(value),
+                value => json!(value.as_str()),
+            };
+            (attribute.key.to_string(), value)
+        })
+        .collect();
+    let (status, status_message) = match &span.status {
+        Status::Unset => ("unset", None),
+        Status::Ok => ("ok", None),
+        Status::Error { description } => ("error", Some(description.to_string())),
+    };
+
+    json!({
+        "trace_id": span.span_context.trace_id().to_string(),
+        "span_id": span.span_context.span_id().to_string(),
+        "parent_span_id": span.parent_span_id.to_string(),
+        "name": span.name,
+        "kind": format!("{:?}", span.span_kind),
+        "start_time_unix_nano": unix_nanos(span.start_time),
+        "end_time_unix_nano": unix_nanos(span.end_time),
+        "attributes": attributes,
+        "status": status,
+        "status_message": status_message,
+    })
+}
@@ -0,0 +1,539 @@
+//! Optional API key authentication, with per key rate limits and daily quotas.
+//!
+//! Keys are read from the JSON file of `API_KEYS_FILE`, which only holds their SHA-256,
+//! so it can be shipped as a Kubernetes secret without exposing the keys themselves.
+//! Without `API_KEYS_FILE` every request is let through and counted as `anonymous`.
+//! The usage of the limited clients is kept in the `api_usage` table, so that every
+//! replica counts against the same limits.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use ::metrics::counter;
+use deadpool_postgres::Pool;
+use eyre::{eyre, WrapErr};
+use rocket::http::Status;
+use rocket::request::{self, FromRequest, Request};
+use rocket::route::{Handler, Outcome};
+use rocket::{Data, Route};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use tracing::{info, warn, Span};
+
+use crate::db;
+use crate::error::ApiError;
+
+/// The header carrying the API key of a request
+pub const API_KEY_HEADER: &str = "X-Api-Key";
+/// The client name of the requests without an API key
+const ANONYMOUS: &str = "anonymous";
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// The limits of a client, every limit is optional
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct Limits {
+    /// Sustained number of requests per minute
+    requests_per_minute: Option<u32>,
+    /// Requests that can be sent at once on top of the sustained rate, defaults to
+    /// `requests_per_minute`
+    burst: Option<u32>,
+    /// Requests per UTC day
+    daily_quota: Option<u64>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct ApiKeyConfig {
+    /// The name the usage of the key is reported under
+    name: String,
+    /// The hex SHA-256 of the key
+    key_sha256: String,
+    requests_per_minute: Option<u32>,
+    burst: Option<u32>,
+    daily_quota: Option<u64>,
+    /// Whether the key can read `/metrics`, which names every client
+    #[serde(default)]
+    metrics: bool,
+}
+
+/// The content of `API_KEYS_FILE`
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct ApiKeysFile {
+    /// The limits of the requests without a key, which are rejected when unset
+    anonymous: Option<Limits>,
+    keys: Vec<ApiKeyConfig>,
+}
+
+/// What a client used of its limits, stored in the `api_usage` table
+#[derive(Debug, Clone, PartialEq)]
+struct Usage {
+    /// Requests that can be sent right away, refilled at the sustained rate
+    tokens: f64,
+    /// When `tokens` was last refilled, as a unix timestamp
+    refilled_at: f64,
+    /// The UTC day `requests_today` counts the requests of
+    day: u64,
+    requests_today: u64,
+}
+
+struct Client {
+    name: String,
+    limits: Limits,
+    /// Whether the client can read `/metrics`
+    metrics: bool,
+}
+
+impl Client {
+    fn new(name: String, limits: Limits, metrics: bool) -> Self {
+        Self {
+            name,
+            limits,
+            metrics,
+        }
+    }
+
+    /// Whether the requests of the client have to be counted
+    fn is_limited(&self) -> bool {
+        self.limits.requests_per_minute.is_some() || self.limits.daily_quota.is_some()
+    }
+
+    /// The requests the client can send at once, and the tokens of a new client
+    fn capacity(&self) -> f64 {
+        self.limits
+            .burst
+            .or(self.limits.requests_per_minute)
+            .unwrap_or(0) as f64
+    }
+
+    /// A function that counts a request against the limits of the client.
+    /// Parameters:
+    /// * usage: What the client used so far, updated with the request
+    /// * now: The current unix timestamp
+    ///
+    /// Returns:
+    /// * Nothing, or the `RateLimited` or `QuotaExceeded` error to answer
+    fn admit(&self, usage: &mut Usage, now: f64) -> Result<(), ApiError> {
+        let now_sec = now.max(0.0) as u64;
+        let today = now_sec / SECONDS_PER_DAY;
+        if usage.day != today {
+            usage.day = today;
+            usage.requests_today = 0;
+        }
+        if let Some(quota) = self.limits.daily_quota {
+            if usage.requests_today >= quota {
+                return Err(ApiError::QuotaExceeded {
+                    client: self.name.clone(),
+                    quota,
+                    retry_after_sec: (today + 1) * SECONDS_PER_DAY - now_sec,
+                });
+            }
+        }
+
+        if let Some(requests_per_minute) = self.limits.requests_per_minute {
+            let rate = requests_per_minute as f64 / 60.0;
+            let elapsed = (now - usage.refilled_at).max(0.0);
+            usage.tokens = (usage.tokens + elapsed * rate).min(self.capacity());
+            usage.refilled_at = now;
+            if usage.tokens < 1.0 {
+                let retry_after_sec = if rate > 0.0 {
+                    ((1.0 - usage.tokens) / rate).ceil() as u64
+                } else {
+                    60
+                };
+                return Err(ApiError::RateLimited {
+                    client: self.name.clone(),
+                    retry_after_sec,
+                });
+            }
+            usage.tokens -= 1.0;
+        }
+
+        usage.requests_today += 1;
+        Ok(())
+    }
+
+    /// A function that counts a request against the limits of the client, with the usage
+    /// stored in PostgreSQL so that every replica counts against the same limits. The row
+    /// of the client is locked until the request is counted.
+    /// Parameters:
+    /// * pool: The PostgreSQL pool
+    ///
+    /// Returns:
+    /// * Nothing, or the error to answer
+    async fn count_request(&self, pool: &Pool) -> Result<(), ApiError> {
+        let mut pg_client = db::get_client(pool).await?;
+        let transaction = pg_client.transaction().await?;
+        let row = transaction
+            .query_one(
+                "INSERT INTO api_usage (client, tokens, refilled_at)
+    VALUES ($1, $2, now())
+    ON CONFLICT (client) DO UPDATE SET client = EXCLUDED.client
+    RETURNING tokens,
+        EXTRACT(EPOCH FROM refilled_at)::DOUBLE PRECISION AS refilled_at,
+        day,
+        requests_today,
+        EXTRACT(EPOCH FROM now())::DOUBLE PRECISION AS now;",
+                &[&self.name, &self.capacity()],
+            )
+            .await?;
+        let mut usage = Usage {
+            tokens: row.get("tokens"),
+            refilled_at: row.get("refilled_at"),
+            day: row.get::<_, i64>("day").max(0) as u64,
+            requests_today: row.get::<_, i64>("requests_today").max(0) as u64,
+        };
+        let admitted = self.admit(&mut usage, row.get("now"));
+        transaction
+            .execute(
+                "UPDATE api_usage
+    SET tokens = $2, refilled_at = to_timestamp($3), day = $4, requests_today = $5
+    WHERE client = $1;",
+                &[
+                    &self.name,
+                    &usage.tokens,
+                    &usage.refilled_at,
+                    &(usage.day as i64),
+                    &(usage.requests_today as i64),
+                ],
+            )
+            .await?;
+        transaction.commit().await?;
+        admitted
+    }
+}
+
+/// The API keys and the usage of their clients, managed as Rocket state
+pub struct ApiAuth {
+    enabled: bool,
+    /// The clients by the hex SHA-256 of their key
+    clients: HashMap<String, Arc<Client>>,
+    anonymous: Option<Arc<Client>>,
+}
+
+impl ApiAuth {
+    /// A function that loads the API keys of `API_KEYS_FILE`, or lets every request
+    /// through when it is not set.
+    ///
+    /// Returns:
+    /// * The keys, or an error if the file cannot be read or is invalid
+    pub fn from_env() -> eyre::Result<Self> {
+        let Some(path) = std::env::var("API_KEYS_FILE")
+            .ok()
+            .filter(|path| !path.is_empty())
+        else {
+            info!("API keys are disabled, every request is let through");
+            return Ok(Self {
+                enabled: false,
+                clients: HashMap::new(),
+                anonymous: Some(Arc::new(Client::new(
+                    ANONYMOUS.to_string(),
+                    Limits::default(),
+                    false,
+                ))),
+            });
+        };
+
+        let content =
+            std::fs::read_to_string(&path).wrap_err_with(|| format!("Failed to read {}", path))?;
+        let file: ApiKeysFile =
+            serde_json::from_str(&content).wrap_err_with(|| format!("Invalid {}", path))?;
+
+        let mut clients = HashMap::with_capacity(file.keys.len());
+        for key in file.keys {
+            let key_sha256 = key.key_sha256.to_lowercase();
+            if key_sha256.len() != 64 || !key_sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(eyre!(
+                    "The key_sha256 of {} must be 64 hex characters",
+                    key.name
+                ));
+            }
+            let limits = Limits {
+                requests_per_minute: key.requests_per_minute,
+                burst: key.burst,
+                daily_quota: key.daily_quota,
+            };
+            let client = Arc::new(Client::new(key.name, limits, key.metrics));
+            if clients.insert(key_sha256, client).is_some() {
+                return Err(eyre!("A key is listed twice in {}", path));
+            }
+        }
+        info!(
+            keys = clients.len(),
+            anonymous = file.anonymous.is_some(),
+            "API keys loaded"
+        );
+
+        Ok(Self {
+            enabled: true,
+            clients,
+            anonymous: file
+                .anonymous
+                .map(|limits| Arc::new(Client::new(ANONYMOUS.to_string(), limits, false))),
+        })
+    }
+
+    /// Whether `API_KEYS_FILE` is set, so that requests are counted against the limits of
+    /// their key
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// A function that finds the client of a request and counts the request against its
+    /// limits.
+    /// Parameters:
+    /// * api_key: The value of the `X-Api-Key` header, if any
+    /// * pool: The PostgreSQL pool the usage of the clients is stored in
+    ///
+    /// Returns:
+    /// * The name of the client, or the error to answer
+    async fn authenticate(&self, api_key: Option<&str>, pool: &Pool) -> Result<String, ApiError> {
+        let client = match api_key.filter(|_| self.enabled) {
+            Some(api_key) => {
+                let key_sha256 = hex::encode(Sha256::digest(api_key.as_bytes()));
+                self.clients
+                    .get(&key_sha256)
+                    .ok_or_else(|| ApiError::Unauthorized("invalid API key".to_string()))?
+            }
+            None => self.anonymous.as_ref().ok_or_else(|| {
+                ApiError::Unauthorized(format!("the {} header is required", API_KEY_HEADER))
+            })?,
+        };
+        if client.is_limited() {
+            client.count_request(pool).await?;
+        }
+        Ok(client.name.clone())
+    }
+
+    /// A function that checks that a request can read `/metrics`: any request without
+    /// `API_KEYS_FILE`, as they are all `anonymous`, only the keys with `metrics` otherwise.
+    /// Scrapes are not counted against the limits of the key.
+    /// Parameters:
+    /// * api_key: The API key of the request, if any
+    ///
+    /// Returns:
+    /// * Nothing, or the error to answer
+    fn authorize_metrics(&self, api_key: Option<&str>) -> Result<(), ApiError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        let api_key = api_key.ok_or_else(|| {
+            ApiError::Unauthorized(format!("the {} header is required", API_KEY_HEADER))
+        })?;
+        let key_sha256 = hex::encode(Sha256::digest(api_key.as_bytes()));
+        match self.clients.get(&key_sha256) {
+            Some(client) if client.metrics => Ok(()),
+            Some(client) => Err(ApiError::Unauthorized(format!(
+                "the API key of {} cannot read the metrics",
+                client.name
+            ))),
+            None => Err(ApiError::Unauthorized("invalid API key".to_string())),
+        }
+    }
+}
+
+/// A request allowed to read `/metrics`. The key is read from `X-Api-Key`, or from
+/// `Authorization: Bearer <key>` as sent by Prometheus.
+pub struct MetricsReader;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for MetricsReader {
+    type Error = ApiError;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let Some(auth) = request.rocket().state::<ApiAuth>() else {
+            return request::Outcome::Error((
+                Status::InternalServerError,
+                ApiError::Internal("API keys are not set up".to_string()),
+            ));
+        };
+        let api_key = request.headers().get_one(API_KEY_HEADER).or_else(|| {
+            request
+                .headers()
+                .get_one("Authorization")
+                .and_then(|value| value.strip_prefix("Bearer "))
+        });
+        match auth.authorize_metrics(api_key) {
+            Ok(()) => request::Outcome::Success(MetricsReader),
+            Err(e) => {
+                warn!(reason = e.code(), "Metrics scrape refused");
+                request::Outcome::Error((e.status(), e))
+            }
+        }
+    }
+}
+
+/// The client a request was authenticated as, `anonymous` without an API key
+pub struct ApiClient {
+    pub name: String,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ApiClient {
+    type Error = ApiError;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let (Some(auth), Some(pool)) = (
+            request.rocket().state::<ApiAuth>(),
+            request.rocket().state::<Pool>(),
+        ) else {
+            return request::Outcome::Error((
+                Status::InternalServerError,
+                ApiError::Internal("API keys are not set up".to_string()),
+            ));
+        };
+        // A request is only counted once, however many times the guard runs
+        let result = request
+            .local_cache_async(async {
+                let result = auth
+                    .authenticate(request.headers().get_one(API_KEY_HEADER), pool)
+                    .await;
+                match &result {
+                    Ok(name) => {
+                        counter!("api_requests_total", "client" => name.clone()).increment(1);
+                    }
+                    Err(e) => {
+                        let client = match e {
+                            ApiError::RateLimited { client, .. }
+                            | ApiError::QuotaExceeded { client, .. } => client.clone(),
+                            _ => "unknown".to_string(),
+                        };
+                        warn!(client, reason = e.code(), "Request refused");
+                        counter!(
+                            "api_requests_rejected_total",
+                            "client" => client,
+                            "reason" => e.code()
+                        )
+                        .increment(1);
+                    }
+                }
+                result
+            })
+            .await;
+        match result {
+            Ok(name) => request::Outcome::Success(ApiClient { name: name.clone() }),
+            Err(e) => request::Outcome::Error((e.status(), e.clone())),
+        }
+    }
+}
+
+/// Runs the [`ApiClient`] guard before a route handler, and answers with its error when
+/// the request is refused
+#[derive(Clone)]
+struct Authenticated(Box<dyn Handler>);
+
+#[rocket::async_trait]
+impl Handler for Authenticated {
+    async fn handle<'r>(&self, request: &'r Request<'_>, data: Data<'r>) -> Outcome<'r> {
+        match request.guard::<ApiClient>().await {
+            request::Outcome::Success(client) => {
+                // Runs in the request span of `logging::traced`
+                Span::current().record("api_client", client.name.as_str());
+                self.0.handle(request, data).await
+            }
+            request::Outcome::Error((_, error)) => Outcome::from(request, error),
+            request::Outcome::Forward(status) => Outcome::Forward((data, status)),
+        }
+    }
+}
+
+/// A function that puts some routes behind the API keys.
+/// Parameters:
+/// * routes: The routes to mount
+///
+/// Returns:
+/// * The same routes, refusing the requests without a valid key or over their limits
+pub fn authenticated(routes: Vec<Route>) -> Vec<Route> {
+    routes
+        .into_iter()
+        .map(|mut route| {
+            route.handler = Box::new(Authenticated(route.handler));
+            route
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Noon of a UTC day, as a unix timestamp
+    const NOON: f64 = (20_000 * SECONDS_PER_DAY + SECONDS_PER_DAY / 2) as f64;
+
+    fn client(
+        requests_per_minute: Option<u32>,
+        burst: Option<u32>,
+        daily_quota: Option<u64>,
+    ) -> Client {
+        let limits = Limits {
+            requests_per_minute,
+            burst,
+            daily_quota,
+        };
+        Client::new("dashboard".to_string(), limits, false)
+    }
+
+    fn usage(client: &Client, now: f64) -> Usage {
+        Usage {
+            tokens: client.capacity(),
+            refilled_at: now,
+            day: 0,
+            requests_today: 0,
+        }
+    }
+
+    #[test]
+    fn burst_is_spent_then_refilled_at_the_sustained_rate() {
+        let client = client(Some(60), Some(3), None);
+        let mut usage = usage(&client, NOON);
+        for _ in 0..3 {
+            assert!(client.admit(&mut usage, NOON).is_ok());
+        }
+        match client.admit(&mut usage, NOON) {
+            Err(ApiError::RateLimited {
+                retry_after_sec, ..
+            }) => assert_eq!(retry_after_sec, 1),
+            other => panic!("expected RateLimited, got {:?}", other),
+        }
+
+        // One request per second comes back, up to the burst
+        assert!(client.admit(&mut usage, NOON + 1.0).is_ok());
+        assert!(client.admit(&mut usage, NOON + 1.0).is_err());
+        assert!(client.admit(&mut usage, NOON + 60.0).is_ok());
+        assert_eq!(usage.tokens, 2.0);
+    }
+
+    #[test]
+    fn quota_starts_over_at_the_next_utc_day() {
+        let client = client(None, None, Some(2));
+        let mut usage = usage(&client, NOON);
+        assert!(client.admit(&mut usage, NOON).is_ok());
+        assert!(client.admit(&mut usage, NOON + 1.0).is_ok());
+        match client.admit(&mut usage, NOON + 2.0) {
+            Err(ApiError::QuotaExceeded {
+                quota,
+                retry_after_sec,
+                ..
+            }) => {
+                assert_eq!(quota, 2);
+                assert_eq!(retry_after_sec, SECONDS_PER_DAY / 2 - 2);
+            }
+            other => panic!("expected QuotaExceeded, got {:?}", other),
+        }
+
+        let midnight = NOON + (SECONDS_PER_DAY / 2) as f64;
+        assert!(client.admit(&mut usage, midnight - 1.0).is_err());
+        assert!(client.admit(&mut usage, midnight).is_ok());
+        assert_eq!(usage.requests_today, 1);
+    }
+
+    #[test]
+    fn refused_requests_are_not_counted_against_the_quota() {
+        let client = client(Some(1), Some(1), Some(10));
+        let mut usage = usage(&client, NOON);
+        assert!(client.admit(&mut usage, NOON).is_ok());
+        assert!(client.admit(&mut usage, NOON + 1.0).is_err());
+        assert_eq!(usage.requests_today, 1);
+    }
+}
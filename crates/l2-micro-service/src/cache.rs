@@ -0,0 +1,610 @@
+//! In-process cache of the output root lookups, and the HTTP caching headers of their
+//! responses.
+//!
+//! Only final outputs are cached: legacy and Arbitrum outputs with their preimage, and
+//! dispute games that resolved DEFENDER_WINS. A final output can still stop being the
+//! answer for a block when another output lands between the two, so entries are dropped
+//! on the events the indexer publishes (see [`crate::live`]). The cache is only used
+//! while these events are listened to.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::Cursor;
+use std::sync::{Arc, Mutex};
+
+use ::metrics::counter;
+use common::LiveEvent;
+use rocket::http::{ContentType, Header, Status};
+use rocket::response::{self, Responder, Response};
+use rocket::Request;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tracing::{debug, info};
+
+use crate::auth::ApiAuth;
+use crate::games::GameStatus;
+use crate::queries::{LookupMode, OutputType};
+
+/// Default number of cached lookups, override with `OUTPUT_CACHE_MAX_ENTRIES`, 0 disables
+/// the cache
+const DEFAULT_MAX_ENTRIES: usize = 100_000;
+/// `max-age` of the responses that can never change
+const IMMUTABLE_MAX_AGE_SEC: u64 = 31_536_000;
+/// `max-age` of the legacy outputs, which the L2OutputOracle challenger can delete until
+/// they are finalized
+const LEGACY_MAX_AGE_SEC: u64 = 3600;
+/// `Cache-Control` of the other responses, to be revalidated with their `ETag`
+const REVALIDATE_CACHE_CONTROL: &str = "no-cache";
+
+/// A lookup: the network name, the requested block and how the output is matched to it
+type CacheKey = (String, i64, LookupMode);
+
+struct CachedOutput {
+    output: OutputType,
+    /// The L2 block of the output, at or after the requested block for covering lookups
+    l2_block_number: i64,
+    /// When the entry was last inserted or read, its key in `CacheState::recency`
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct CacheState {
+    /// Whether the indexer events are listened to, nothing is cached otherwise
+    enabled: bool,
+    /// Bumped on every invalidation, so that a lookup that raced with it is not cached
+    generation: u64,
+    entries: HashMap<CacheKey, CachedOutput>,
+    /// The keys of the entries by when they were last used, the least recent first
+    recency: BTreeMap<u64, CacheKey>,
+    /// Ticks on every insert and read, orders `recency`
+    clock: u64,
+}
+
+impl CacheState {
+    /// A function that returns a cached output and marks it as the most recently used
+    fn touch(&mut self, key: &CacheKey) -> Option<&CachedOutput> {
+        self.clock += 1;
+        let cached = self.entries.get_mut(key)?;
+        let key = self.recency.remove(&cached.last_used)?;
+        cached.last_used = self.clock;
+        self.recency.insert(self.clock, key);
+        Some(cached)
+    }
+
+    /// A function that caches an output as the most recently used, evicting the least
+    /// recently used entries beyond `max_entries`
+    fn insert(&mut self, key: CacheKey, output: &OutputType, max_entries: usize) {
+        self.clock += 1;
+        let cached = CachedOutput {
+            output: output.clone(),
+            l2_block_number: l2_block_number(output),
+            last_used: self.clock,
+        };
+        if let Some(replaced) = self.entries.insert(key.clone(), cached) {
+            self.recency.remove(&replaced.last_used);
+        }
+        self.recency.insert(self.clock, key);
+        while self.entries.len() > max_entries {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+            counter!("output_cache_evictions_total").increment(1);
+        }
+    }
+
+    /// A function that keeps the entries for which `keep` is true
+    fn retain(&mut self, mut keep: impl FnMut(&CacheKey, &CachedOutput) -> bool) {
+        let entries = &mut self.entries;
+        self.recency.retain(|_, key| {
+            let kept = entries.get(key).is_some_and(|cached| keep(key, cached));
+            if !kept {
+                entries.remove(key);
+            }
+            kept
+        });
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+    }
+}
+
+/// The cached output root lookups, shared by the routes and the live events feed
+#[derive(Clone)]
+pub struct OutputCache {
+    max_entries: usize,
+    state: Arc<Mutex<CacheState>>,
+}
+
+impl OutputCache {
+    pub fn from_env() -> eyre::Result<Self> {
+        let max_entries = match std::env::var("OUTPUT_CACHE_MAX_ENTRIES") {
+            Ok(value) if !value.is_empty() => value
+                .parse()
+                .map_err(|e| eyre::eyre!("Invalid OUTPUT_CACHE_MAX_ENTRIES: {}", e))?,
+            _ => DEFAULT_MAX_ENTRIES,
+        };
+        info!(max_entries, "Output cache configured");
+        Ok(Self {
+            max_entries,
+            state: Arc::new(Mutex::new(CacheState::default())),
+        })
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, CacheState> {
+        // The state is left consistent by every critical section, even one that panicked
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// A function that returns the cached output of a lookup.
+    /// Parameters:
+    /// * network: The network name
+    /// * l2_block: The requested block
+    /// * mode: How the output is matched to the block
+    ///
+    /// Returns:
+    /// * The output, or None if it is not cached
+    pub fn get(&self, network: &str, l2_block: i64, mode: LookupMode) -> Option<OutputType> {
+        if self.max_entries == 0 {
+            return None;
+        }
+        let output = self
+            .state()
+            .touch(&(network.to_string(), l2_block, mode))
+            .map(|cached| cached.output.clone());
+        let result = if output.is_some() { "hit" } else { "miss" };
+        counter!("output_cache_lookups_total", "result" => result).increment(1);
+        output
+    }
+
+    /// A function that returns the generation to pass to [`OutputCache::insert`], to be
+    /// taken before the database is queried.
+    ///
+    /// Returns:
+    /// * The generation, or None while the cache is not in use
+    pub fn generation(&self) -> Option<u64> {
+        let state = self.state();
+        (self.max_entries > 0 && state.enabled).then_some(state.generation)
+    }
+
+    /// A function that caches the output of a lookup if it is final and nothing was
+    /// invalidated since `generation` was taken.
+    /// Parameters:
+    /// * network: The network name
+    /// * l2_block: The requested block
+    /// * mode: How the output is matched to the block
+    /// * generation: The generation taken before the lookup
+    /// * output: The output found
+    pub fn insert(
+        &self,
+        network: &str,
+        l2_block: i64,
+        mode: LookupMode,
+        generation: Option<u64>,
+        output: &OutputType,
+    ) {
+        if !is_final(output) {
+            return;
+        }
+        let mut state = self.state();
+        if generation.is_none() || generation != Some(state.generation) || !state.enabled {
+            return;
+        }
+        state.insert(
+            (network.to_string(), l2_block, mode),
+            output,
+            self.max_entries,
+        );
+    }
+
+    /// A function that drops the lookups whose answer may have changed with an event:
+    /// the lookups of the block of the event, and the lookups between the block of the
+    /// event and the block of their output.
+    pub fn invalidate(&self, event: &LiveEvent) {
+        let mut state = self.state();
+        state.generation += 1;
+        let block = event
+            .data
+            .get("l2_block_number")
+            .and_then(|block| block.as_i64());
+        let before = state.entries.len();
+        state.retain(|(network, l2_block, mode), cached| {
+            if *network != event.network {
+                return true;
+            }
+            let Some(block) = block else {
+                return false;
+            };
+            match mode {
+                LookupMode::Covering => !(*l2_block <= block && block <= cached.l2_block_number),
+                LookupMode::Preceding => !(cached.l2_block_number <= block && block <= *l2_block),
+                LookupMode::Exact => *l2_block != block,
+            }
+        });
+        debug!(
+            event_id = %event.id,
+            dropped = before - state.entries.len(),
+            "Invalidated the output cache"
+        );
+    }
+
+    /// A function that starts using the cache, once the indexer events are listened to
+    pub fn enable(&self) {
+        let mut state = self.state();
+        state.enabled = true;
+        state.generation += 1;
+    }
+
+    /// A function that empties the cache and stops using it, as events may be missed
+    /// until they are listened to again
+    pub fn disable(&self) {
+        let mut state = self.state();
+        state.enabled = false;
+        state.generation += 1;
+        state.clear();
+    }
+}
+
+fn l2_block_number(output: &OutputType) -> i64 {
+    match output {
+        OutputType::OpStack(output) => output.l2_block_number.into(),
+        OutputType::OpStackFDG(output) => output.l2_block_number,
+        OutputType::Arbitrum(output) => output.l2_block_number.into(),
+    }
+}
+
+/// A function that tells whether an output will be served the same way for as long as
+/// it is the answer: a legacy output whose preimage is stored, an Arbitrum output, or a
+/// dispute game that resolved DEFENDER_WINS.
+fn is_final(output: &OutputType) -> bool {
+    match output {
+        OutputType::OpStack(output) => {
+            output.l2_state_root.is_some()
+                && output.l2_withdrawal_storage_root.is_some()
+                && output.l2_block_hash.is_some()
+        }
+        OutputType::OpStackFDG(output) => {
+            GameStatus::from_game_state(output.game_state) == Some(GameStatus::DefenderWins)
+        }
+        OutputType::Arbitrum(_) => true,
+    }
+}
+
+/// How long clients and caches can reuse a response without revalidating it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Freshness {
+    /// Revalidated with its `ETag` on every use
+    Revalidate,
+    /// Reused for the given number of seconds
+    MaxAge(u64),
+    /// Can never change
+    Immutable,
+}
+
+/// A function that tells how long the answer of a lookup can be reused by clients and
+/// CDNs. Legacy and Arbitrum outputs are proposed in L2 block order, so no output can
+/// land before the one covering a block. A legacy output can still be deleted by the
+/// L2OutputOracle challenger until it is finalized, so it is only reused for
+/// `LEGACY_MAX_AGE_SEC`. Dispute games can claim any block, so only a game at exactly the
+/// requested block is immutable.
+/// Parameters:
+/// * output: The output found
+/// * l2_block: The requested block
+/// * mode: How the output was matched to the block
+pub fn freshness(output: &OutputType, l2_block: i64, mode: LookupMode) -> Freshness {
+    if !is_final(output) {
+        return Freshness::Revalidate;
+    }
+    let exact_hit = l2_block_number(output) == l2_block;
+    let settled = match output {
+        OutputType::OpStack(_) | OutputType::Arbitrum(_) => {
+            mode != LookupMode::Preceding || exact_hit
+        }
+        OutputType::OpStackFDG(_) => exact_hit,
+    };
+    if !settled {
+        return Freshness::Revalidate;
+    }
+    match output {
+        OutputType::OpStack(_) => Freshness::MaxAge(LEGACY_MAX_AGE_SEC),
+        OutputType::OpStackFDG(_) | OutputType::Arbitrum(_) => Freshness::Immutable,
+    }
+}
+
+/// A function that returns the `Cache-Control` of a response.
+/// Parameters:
+/// * freshness: How long the response can be reused
+/// * shared: Whether shared caches may store it, they must not once API keys are required,
+///   as they would serve it to requests without a key and outside of their limits
+fn cache_control(freshness: Freshness, shared: bool) -> String {
+    let visibility = if shared { "public" } else { "private" };
+    match freshness {
+        Freshness::Revalidate => REVALIDATE_CACHE_CONTROL.to_string(),
+        Freshness::MaxAge(max_age) => format!("{}, max-age={}", visibility, max_age),
+        Freshness::Immutable => format!(
+            "{}, max-age={}, immutable",
+            visibility, IMMUTABLE_MAX_AGE_SEC
+        ),
+    }
+}
+
+/// A JSON response with an `ETag` and a `Cache-Control` header. A request whose
+/// `If-None-Match` matches the `ETag` is answered 304 Not Modified without a body.
+pub struct CachedJson<T> {
+    pub body: T,
+    pub freshness: Freshness,
+}
+
+impl<'r, T: Serialize> Responder<'r, 'static> for CachedJson<T> {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let body = serde_json::to_string(&self.body).map_err(|_| Status::InternalServerError)?;
+        let etag = format!("\"{}\"", hex::encode(&Sha256::digest(&body)[..16]));
+        let shared = !request
+            .rocket()
+            .state::<ApiAuth>()
+            .is_some_and(ApiAuth::enabled);
+        let cache_control = cache_control(self.freshness, shared);
+
+        let mut response = Response::build();
+        response
+            .header(Header::new("ETag", etag.clone()))
+            .header(Header::new("Cache-Control", cache_control));
+        if if_none_match(request, &etag) {
+            return response.status(Status::NotModified).ok();
+        }
+        response
+            .header(ContentType::JSON)
+            .sized_body(body.len(), Cursor::new(body))
+            .ok()
+    }
+}
+
+/// A function that tells whether `If-None-Match` lists the ETag, weak ETags included
+fn if_none_match(request: &Request<'_>, etag: &str) -> bool {
+    request
+        .headers()
+        .get("If-None-Match")
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::queries::{
+        ArbitrumParamsOutput, OPStackFaultDisputeGameOutput, OPStackParamsOutput,
+    };
+    use common::LiveEventType;
+    use serde_json::json;
+
+    fn cache(max_entries: usize) -> OutputCache {
+        let cache = OutputCache {
+            max_entries,
+            state: Arc::new(Mutex::new(CacheState::default())),
+        };
+        cache.enable();
+        cache
+    }
+
+    fn arbitrum_output(l2_block_number: i32) -> OutputType {
+        OutputType::Arbitrum(ArbitrumParamsOutput {
+            l2_output_root: "0x01".to_string(),
+            l2_block_hash: "0x02".to_string(),
+            l2_block_number,
+            l1_transaction_hash: "0x03".to_string(),
+            l1_block_number: 1,
+            l1_transaction_index: 0,
+            l1_block_hash: "0x04".to_string(),
+        })
+    }
+
+    fn opstack_output(l2_block_number: i32, with_preimage: bool) -> OutputType {
+        let word = with_preimage.then(|| format!("0x{}", "11".repeat(32)));
+        OutputType::OpStack(OPStackParamsOutput {
+            l2_output_root: "0x01".to_string(),
+            l2_output_index: 0,
+            l2_block_number,
+            l1_timestamp: 0,
+            l1_transaction_hash: "0x03".to_string(),
+            l1_block_number: 1,
+            l1_transaction_index: 0,
+            l1_block_hash: "0x04".to_string(),
+            l2_state_root: word.clone(),
+            l2_withdrawal_storage_root: word.clone(),
+            l2_block_hash: word,
+        })
+    }
+
+    fn game_output(l2_block_number: i64, game_state: i64) -> OutputType {
+        OutputType::OpStackFDG(OPStackFaultDisputeGameOutput {
+            game_index: 0,
+            game_address: "0x05".to_string(),
+            game_type: 0,
+            timestamp: 0,
+            root_claim: "0x01".to_string(),
+            game_state,
+            proposer_address: "0x06".to_string(),
+            l2_block_number,
+            l2_state_root: None,
+            l2_withdrawal_storage_root: None,
+            l2_block_hash: None,
+            l1_transaction_hash: "0x03".to_string(),
+            l1_block_number: 1,
+            l1_transaction_index: 0,
+            l1_block_hash: "0x04".to_string(),
+            version_byte: "0x00".to_string(),
+        })
+    }
+
+    fn output_event(network: &str, l2_block_number: Option<i64>) -> LiveEvent {
+        LiveEvent::new(
+            network,
+            LiveEventType::OutputProposed,
+            l2_block_number.unwrap_or_default() as u64,
+            json!({ "l2_block_number": l2_block_number }),
+        )
+    }
+
+    fn insert(cache: &OutputCache, l2_block: i64, output: &OutputType) {
+        let generation = cache.generation();
+        cache.insert(
+            "arbitrum_mainnet",
+            l2_block,
+            LookupMode::Covering,
+            generation,
+            output,
+        );
+    }
+
+    fn cached(cache: &OutputCache, l2_block: i64) -> bool {
+        cache
+            .get("arbitrum_mainnet", l2_block, LookupMode::Covering)
+            .is_some()
+    }
+
+    #[test]
+    fn full_cache_evicts_the_least_recently_used_lookup() {
+        let cache = cache(2);
+        insert(&cache, 10, &arbitrum_output(10));
+        insert(&cache, 20, &arbitrum_output(20));
+        // Reading 10 makes 20 the least recently used
+        assert!(cached(&cache, 10));
+        insert(&cache, 30, &arbitrum_output(30));
+
+        assert!(cached(&cache, 10));
+        assert!(!cached(&cache, 20));
+        assert!(cached(&cache, 30));
+        assert_eq!(cache.state().recency.len(), 2);
+    }
+
+    #[test]
+    fn events_drop_the_lookups_they_may_change() {
+        let cache = cache(100);
+        let lookups = [
+            // Requested block, mode, block of the output
+            (15, LookupMode::Covering, 20),
+            (5, LookupMode::Covering, 10),
+            (18, LookupMode::Exact, 18),
+            (19, LookupMode::Exact, 19),
+            (20, LookupMode::Preceding, 16),
+            (30, LookupMode::Preceding, 25),
+        ];
+        for (l2_block, mode, output_block) in lookups {
+            let generation = cache.generation();
+            let output = arbitrum_output(output_block);
+            cache.insert("arbitrum_mainnet", l2_block, mode, generation, &output);
+        }
+
+        cache.invalidate(&output_event("arbitrum_mainnet", Some(18)));
+        let kept: Vec<bool> = lookups
+            .iter()
+            .map(|(l2_block, mode, _)| cache.get("arbitrum_mainnet", *l2_block, *mode).is_some())
+            .collect();
+        assert_eq!(kept, [false, true, false, true, false, true]);
+
+        // Other networks are left alone, and an event without a block drops every lookup
+        cache.invalidate(&output_event("arbitrum_sepolia", None));
+        assert!(cached(&cache, 5));
+        cache.invalidate(&output_event("arbitrum_mainnet", None));
+        assert!(!cached(&cache, 5));
+        assert!(cache.state().recency.is_empty());
+    }
+
+    #[test]
+    fn lookups_racing_with_an_invalidation_are_not_cached() {
+        let cache = cache(100);
+        let generation = cache.generation();
+        cache.invalidate(&output_event("arbitrum_mainnet", Some(18)));
+        cache.insert(
+            "arbitrum_mainnet",
+            15,
+            LookupMode::Covering,
+            generation,
+            &arbitrum_output(20),
+        );
+        assert!(!cached(&cache, 15));
+
+        // Nor while the events are not listened to
+        cache.disable();
+        assert_eq!(cache.generation(), None);
+        insert(&cache, 15, &arbitrum_output(20));
+        assert!(!cached(&cache, 15));
+    }
+
+    #[test]
+    fn only_final_outputs_are_cached() {
+        assert!(is_final(&arbitrum_output(10)));
+        assert!(is_final(&opstack_output(10, true)));
+        assert!(!is_final(&opstack_output(10, false)));
+        assert!(is_final(&game_output(10, 2)));
+        assert!(!is_final(&game_output(10, 0)));
+        assert!(!is_final(&game_output(10, 1)));
+
+        let cache = cache(100);
+        insert(&cache, 10, &game_output(10, 0));
+        assert!(!cached(&cache, 10));
+    }
+
+    #[test]
+    fn only_answers_no_later_output_can_change_are_reused() {
+        let legacy = opstack_output(20, true);
+        let legacy_max_age = Freshness::MaxAge(LEGACY_MAX_AGE_SEC);
+        assert_eq!(freshness(&legacy, 15, LookupMode::Covering), legacy_max_age);
+        assert_eq!(freshness(&legacy, 20, LookupMode::Exact), legacy_max_age);
+        assert_eq!(
+            freshness(&legacy, 25, LookupMode::Preceding),
+            Freshness::Revalidate
+        );
+        assert_eq!(
+            freshness(&legacy, 20, LookupMode::Preceding),
+            legacy_max_age
+        );
+        assert_eq!(
+            freshness(&opstack_output(20, false), 20, LookupMode::Exact),
+            Freshness::Revalidate
+        );
+
+        let arbitrum = arbitrum_output(20);
+        assert_eq!(
+            freshness(&arbitrum, 15, LookupMode::Covering),
+            Freshness::Immutable
+        );
+        assert_eq!(
+            freshness(&arbitrum, 25, LookupMode::Preceding),
+            Freshness::Revalidate
+        );
+
+        let game = game_output(20, 2);
+        assert_eq!(
+            freshness(&game, 15, LookupMode::Covering),
+            Freshness::Revalidate
+        );
+        assert_eq!(
+            freshness(&game, 20, LookupMode::Covering),
+            Freshness::Immutable
+        );
+        assert_eq!(
+            freshness(&game_output(20, 0), 20, LookupMode::Exact),
+            Freshness::Revalidate
+        );
+    }
+
+    #[test]
+    fn reused_responses_are_private_once_api_keys_are_required() {
+        assert_eq!(
+            cache_control(Freshness::Immutable, true),
+            "public, max-age=31536000, immutable"
+        );
+        assert_eq!(
+            cache_control(Freshness::Immutable, false),
+            "private, max-age=31536000, immutable"
+        );
+        assert_eq!(
+            cache_control(Freshness::MaxAge(LEGACY_MAX_AGE_SEC), false),
+            "private, max-age=3600"
+        );
+        assert_eq!(cache_control(Freshness::Revalidate, false), "no-cache");
+    }
+}
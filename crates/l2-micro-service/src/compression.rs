@@ -0,0 +1,207 @@
+//! Compression of the JSON and text responses, with brotli or gzip depending on the
+//! `Accept-Encoding` of the request.
+
+use std::io::{Cursor, Write};
+
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{ContentType, Header};
+use rocket::{Request, Response};
+use tracing::{info, warn};
+
+/// Default size in bytes below which responses are sent as is, override with
+/// `COMPRESSION_MIN_BYTES`
+const DEFAULT_MIN_BYTES: usize = 1024;
+/// Brotli quality, from 0 to 11, trading a bit of ratio for a lot of speed
+const BROTLI_QUALITY: u32 = 5;
+const BROTLI_WINDOW_BITS: u32 = 22;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+impl Encoding {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+        }
+    }
+
+    fn compress(&self, body: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Encoding::Brotli => {
+                let mut writer = brotli::CompressorWriter::new(
+                    Vec::new(),
+                    4096,
+                    BROTLI_QUALITY,
+                    BROTLI_WINDOW_BITS,
+                );
+                writer.write_all(body)?;
+                Ok(writer.into_inner())
+            }
+            Encoding::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(body)?;
+                encoder.finish()
+            }
+        }
+    }
+}
+
+/// A function that picks the encoding of a response from `Accept-Encoding`, brotli
+/// first, ignoring the codings refused with `q=0`.
+/// Parameters:
+/// * accept_encoding: The `Accept-Encoding` headers of the request
+fn preferred_encoding<'a>(accept_encoding: impl Iterator<Item = &'a str>) -> Option<Encoding> {
+    let mut accepted = Vec::new();
+    for coding in accept_encoding.flat_map(|value| value.split(',')) {
+        let mut params = coding.split(';').map(str::trim);
+        let name = params.next().unwrap_or_default().to_lowercase();
+        let refused = params
+            .filter_map(|param| param.strip_prefix("q="))
+            .any(|q| q.parse::<f32>().is_ok_and(|q| q <= 0.0));
+        if !refused {
+            accepted.push(name);
+        }
+    }
+    [Encoding::Brotli, Encoding::Gzip]
+        .into_iter()
+        .find(|encoding| {
+            accepted
+                .iter()
+                .any(|name| name == encoding.as_str() || name == "*")
+        })
+}
+
+/// Compresses the JSON and text responses of at least `COMPRESSION_MIN_BYTES`. Streams,
+/// such as the `/v1/events` one, are sent as is. The `ETag` of a compressed response is
+/// made weak, as its bytes differ from the uncompressed ones.
+pub struct CompressionFairing {
+    enabled: bool,
+    min_bytes: usize,
+}
+
+impl CompressionFairing {
+    pub fn from_env() -> eyre::Result<Self> {
+        let enabled = std::env::var("COMPRESSION_ENABLED")
+            .map(|value| !(value.eq_ignore_ascii_case("false") || value == "0"))
+            .unwrap_or(true);
+        let min_bytes = match std::env::var("COMPRESSION_MIN_BYTES") {
+            Ok(value) if !value.is_empty() => value
+                .parse()
+                .map_err(|e| eyre::eyre!("Invalid COMPRESSION_MIN_BYTES: {}", e))?,
+            _ => DEFAULT_MIN_BYTES,
+        };
+        info!(enabled, min_bytes, "Response compression configured");
+        Ok(Self { enabled, min_bytes })
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for CompressionFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "Response compression",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        if !self.enabled || response.headers().contains("Content-Encoding") {
+            return;
+        }
+        let compressible = response
+            .content_type()
+            .is_some_and(|content_type| compressible(&content_type));
+        // Streamed bodies have no preset size
+        let large_enough = response
+            .body()
+            .preset_size()
+            .is_some_and(|size| size >= self.min_bytes);
+        if !compressible || !large_enough {
+            return;
+        }
+
+        response.adjoin_header(Header::new("Vary", "Accept-Encoding"));
+        let Some(encoding) = preferred_encoding(request.headers().get("Accept-Encoding")) else {
+            return;
+        };
+        let body = match response.body_mut().to_bytes().await {
+            Ok(body) => body,
+            Err(e) => {
+                warn!(error = %e, "Failed to read the response body to compress");
+                return;
+            }
+        };
+        let compressed = match encoding.compress(&body) {
+            Ok(compressed) if compressed.len() < body.len() => compressed,
+            Ok(_) => {
+                response.set_sized_body(body.len(), Cursor::new(body));
+                return;
+            }
+            Err(e) => {
+                warn!(error = %e, encoding = encoding.as_str(), "Failed to compress a response");
+                response.set_sized_body(body.len(), Cursor::new(body));
+                return;
+            }
+        };
+
+        if let Some(etag) = response
+            .headers()
+            .get_one("ETag")
+            .filter(|etag| !etag.starts_with("W/"))
+        {
+            let weak = format!("W/{}", etag);
+            response.set_header(Header::new("ETag", weak));
+        }
+        response.set_header(Header::new("Content-Encoding", encoding.as_str()));
+        response.set_sized_body(compressed.len(), Cursor::new(compressed));
+    }
+}
+
+fn compressible(content_type: &ContentType) -> bool {
+    content_type.is_json() || (content_type.top() == "text" && content_type.sub() != "event-stream")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preferred(accept_encoding: &[&str]) -> Option<Encoding> {
+        preferred_encoding(accept_encoding.iter().copied())
+    }
+
+    #[test]
+    fn brotli_is_preferred_over_gzip() {
+        assert_eq!(preferred(&["gzip, deflate, br"]), Some(Encoding::Brotli));
+        assert_eq!(preferred(&["gzip", "BR;q=0.5"]), Some(Encoding::Brotli));
+        assert_eq!(preferred(&["gzip, deflate"]), Some(Encoding::Gzip));
+        assert_eq!(preferred(&["*"]), Some(Encoding::Brotli));
+        assert_eq!(preferred(&["identity"]), None);
+        assert_eq!(preferred(&[]), None);
+    }
+
+    #[test]
+    fn codings_refused_with_a_zero_quality_are_skipped() {
+        assert_eq!(preferred(&["br;q=0, gzip"]), Some(Encoding::Gzip));
+        assert_eq!(preferred(&["br; q=0.0, gzip;q=0"]), None);
+        assert_eq!(preferred(&["br;q=0.001"]), Some(Encoding::Brotli));
+    }
+
+    #[test]
+    fn compressed_bodies_round_trip() {
+        let body = br#"{"l2_output_root":"0x01"}"#.repeat(100);
+        let compressed = Encoding::Gzip.compress(&body).unwrap();
+        let mut decompressed = Vec::new();
+        std::io::Read::read_to_end(
+            &mut flate2::read::GzDecoder::new(compressed.as_slice()),
+            &mut decompressed,
+        )
+        .unwrap();
+        assert_eq!(decompressed, body);
+        assert!(Encoding::Brotli.compress(&body).unwrap().len() < body.len());
+    }
+}
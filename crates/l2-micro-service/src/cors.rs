@@ -0,0 +1,229 @@
+//! Cross-origin resource sharing, so that browser based tooling can call the API.
+//!
+//! CORS is off unless `CORS_ALLOWED_ORIGINS` is set. The preflight requests are answered
+//! by [`preflight`], which is mounted outside of the API keys since browsers never send
+//! credentials with them.
+
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{Header, Method, Status};
+use rocket::{Request, Response};
+use tracing::info;
+
+use crate::auth::API_KEY_HEADER;
+use crate::logging::REQUEST_ID_HEADER;
+
+/// The methods of the API
+const ALLOWED_METHODS: &str = "GET, POST, DELETE, OPTIONS";
+/// The request headers always allowed, `CORS_ALLOWED_HEADERS` adds to them
+const DEFAULT_ALLOWED_HEADERS: &[&str] = &[
+    "Content-Type",
+    "If-None-Match",
+    "Last-Event-ID",
+    "traceparent",
+    "tracestate",
+    API_KEY_HEADER,
+    REQUEST_ID_HEADER,
+];
+/// The response headers scripts may read on top of the CORS safelisted ones
+const EXPOSED_HEADERS: &str = "ETag, Location, Retry-After, X-Request-Id";
+/// Default seconds a preflight is cached by browsers, override with `CORS_MAX_AGE_SEC`
+const DEFAULT_MAX_AGE_SEC: u64 = 3600;
+
+/// An allowed origin: exact, or any subdomain for `https://*.example.com`
+#[derive(Debug)]
+enum OriginPattern {
+    Exact(String),
+    Subdomains { scheme: String, domain: String },
+}
+
+impl OriginPattern {
+    fn parse(origin: &str) -> eyre::Result<Self> {
+        let origin = origin.trim_end_matches('/').to_lowercase();
+        let Some((scheme, host)) = origin.split_once("://") else {
+            return Err(eyre::eyre!(
+                "Invalid CORS origin {}, expected <scheme>://<host>[:<port>]",
+                origin
+            ));
+        };
+        Ok(match host.strip_prefix("*.") {
+            Some(domain) => OriginPattern::Subdomains {
+                scheme: scheme.to_string(),
+                domain: domain.to_string(),
+            },
+            None => OriginPattern::Exact(origin),
+        })
+    }
+
+    fn matches(&self, origin: &str) -> bool {
+        match self {
+            OriginPattern::Exact(allowed) => *allowed == origin,
+            OriginPattern::Subdomains { scheme, domain } => origin
+                .strip_prefix(scheme.as_str())
+                .and_then(|origin| origin.strip_prefix("://"))
+                .and_then(|host| host.strip_suffix(domain.as_str()))
+                .is_some_and(|subdomain| subdomain.len() > 1 && subdomain.ends_with('.')),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum AllowedOrigins {
+    None,
+    Any,
+    Some(Vec<OriginPattern>),
+}
+
+/// Adds the CORS headers to the responses of the allowed origins, and completes the
+/// preflight requests with the allowed methods and headers.
+pub struct CorsFairing {
+    origins: AllowedOrigins,
+    allowed_headers: String,
+    max_age_sec: u64,
+}
+
+impl CorsFairing {
+    pub fn from_env() -> eyre::Result<Self> {
+        let origins = std::env::var("CORS_ALLOWED_ORIGINS").unwrap_or_default();
+        let origins: Vec<&str> = origins
+            .split(',')
+            .map(str::trim)
+            .filter(|origin| !origin.is_empty())
+            .collect();
+        let origins = if origins.is_empty() {
+            AllowedOrigins::None
+        } else if origins.contains(&"*") {
+            AllowedOrigins::Any
+        } else {
+            AllowedOrigins::Some(
+                origins
+                    .into_iter()
+                    .map(OriginPattern::parse)
+                    .collect::<eyre::Result<_>>()?,
+            )
+        };
+
+        let extra_headers = std::env::var("CORS_ALLOWED_HEADERS").unwrap_or_default();
+        let allowed_headers = DEFAULT_ALLOWED_HEADERS
+            .iter()
+            .copied()
+            .chain(
+                extra_headers
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|header| !header.is_empty()),
+            )
+            .collect::<Vec<_>>()
+            .join(", ");
+        let max_age_sec = match std::env::var("CORS_MAX_AGE_SEC") {
+            Ok(value) if !value.is_empty() => value
+                .parse()
+                .map_err(|e| eyre::eyre!("Invalid CORS_MAX_AGE_SEC: {}", e))?,
+            _ => DEFAULT_MAX_AGE_SEC,
+        };
+
+        info!(origins = ?origins, "CORS configured");
+        Ok(Self {
+            origins,
+            allowed_headers,
+            max_age_sec,
+        })
+    }
+
+    /// A function that returns the `Access-Control-Allow-Origin` of a request origin.
+    ///
+    /// Returns:
+    /// * `*`, the origin itself, or None if the origin is not allowed
+    fn allow_origin(&self, origin: &str) -> Option<String> {
+        match &self.origins {
+            AllowedOrigins::None => None,
+            AllowedOrigins::Any => Some("*".to_string()),
+            AllowedOrigins::Some(patterns) => {
+                let lowercase = origin.to_lowercase();
+                patterns
+                    .iter()
+                    .any(|pattern| pattern.matches(&lowercase))
+                    .then(|| origin.to_string())
+            }
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for CorsFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "CORS",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        if matches!(self.origins, AllowedOrigins::Some(_)) {
+            // The answer depends on the origin, shared caches must not mix them up
+            response.adjoin_header(Header::new("Vary", "Origin"));
+        }
+        let Some(allow_origin) = request
+            .headers()
+            .get_one("Origin")
+            .and_then(|origin| self.allow_origin(origin))
+        else {
+            return;
+        };
+
+        response.set_header(Header::new("Access-Control-Allow-Origin", allow_origin));
+        let preflight = request.method() == Method::Options
+            && request.headers().contains("Access-Control-Request-Method");
+        if preflight {
+            response.set_header(Header::new("Access-Control-Allow-Methods", ALLOWED_METHODS));
+            response.set_header(Header::new(
+                "Access-Control-Allow-Headers",
+                self.allowed_headers.clone(),
+            ));
+            response.set_header(Header::new(
+                "Access-Control-Max-Age",
+                self.max_age_sec.to_string(),
+            ));
+        } else {
+            response.set_header(Header::new(
+                "Access-Control-Expose-Headers",
+                EXPOSED_HEADERS,
+            ));
+        }
+    }
+}
+
+/// Answers the preflight requests of every route, [`CorsFairing`] adds the CORS headers
+#[options("/<_..>")]
+pub fn preflight() -> Status {
+    Status::NoContent
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_origins_match_only_themselves() {
+        let pattern = OriginPattern::parse("https://App.example.com/").unwrap();
+        assert!(pattern.matches("https://app.example.com"));
+        assert!(!pattern.matches("http://app.example.com"));
+        assert!(!pattern.matches("https://app.example.com:8443"));
+        assert!(!pattern.matches("https://evil-app.example.com"));
+    }
+
+    #[test]
+    fn wildcard_origins_match_subdomains_only() {
+        let pattern = OriginPattern::parse("https://*.example.com").unwrap();
+        assert!(pattern.matches("https://app.example.com"));
+        assert!(pattern.matches("https://a.b.example.com"));
+        assert!(!pattern.matches("https://example.com"));
+        assert!(!pattern.matches("https://evilexample.com"));
+        assert!(!pattern.matches("https://.example.com"));
+        assert!(!pattern.matches("http://app.example.com"));
+    }
+
+    #[test]
+    fn origins_without_scheme_are_refused() {
+        assert!(OriginPattern::parse("example.com").is_err());
+    }
+}
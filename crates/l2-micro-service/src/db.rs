@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use deadpool_postgres::{
+    Config, ManagerConfig, Pool, PoolConfig, RecyclingMethod, Runtime, Timeouts,
+};
+use eyre::{Result, WrapErr};
+use tokio_postgres::NoTls;
+use tracing::{error, info};
+
+use crate::error::ApiError;
+
+/// Default number of pooled connections, override with `DB_POOL_MAX_SIZE`
+const DEFAULT_POOL_MAX_SIZE: usize = 16;
+/// Default timeout in seconds for waiting on, creating and recycling a connection,
+/// override with `DB_POOL_TIMEOUT_SEC`
+const DEFAULT_POOL_TIMEOUT_SEC: u64 = 5;
+
+/// A function that builds the shared postgres pool from the environment.
+/// Connections are opened lazily and checked with a round trip before being reused,
+/// so a restarted database is recovered from without restarting the service.
+///
+/// Returns:
+/// * The connection pool, to be managed as Rocket state
+pub fn create_pool() -> Result<Pool> {
+    let db_url = std::env::var("DB_URL").wrap_err("DB_URL must be set")?;
+    let max_size = env_or("DB_POOL_MAX_SIZE", DEFAULT_POOL_MAX_SIZE)?;
+    let timeout = Duration::from_secs(env_or("DB_POOL_TIMEOUT_SEC", DEFAULT_POOL_TIMEOUT_SEC)?);
+
+    let mut config = Config::new();
+    config.url = Some(db_url);
+    config.connect_timeout = Some(timeout);
+    config.manager = Some(ManagerConfig {
+        recycling_method: RecyclingMethod::Verified,
+    });
+    config.pool = Some(PoolConfig {
+        max_size,
+        timeouts: Timeouts {
+            wait: Some(timeout),
+            create: Some(timeout),
+            recycle: Some(timeout),
+        },
+        ..Default::default()
+    });
+
+    let pool = config
+        .create_pool(Some(Runtime::Tokio1), NoTls)
+        .wrap_err("Failed to create the PostgreSQL pool")?;
+
+    info!(
+        max_size,
+        timeout_secs = timeout.as_secs(),
+        "PostgreSQL pool ready"
+    );
+
+    Ok(pool)
+}
+
+/// A function that checks a connection out of the pool.
+/// A database that cannot be reached is reported as 503 Service Unavailable.
+pub async fn get_client(pool: &Pool) -> Result<deadpool_postgres::Client, ApiError> {
+    pool.get().await.map_err(|e| {
+        error!(error = %e, "Failed to get a PostgreSQL connection");
+        ApiError::from(e)
+    })
+}
+
+fn env_or<T: std::str::FromStr>(key: &str, default: T) -> Result<T>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    match std::env::var(key) {
+        Ok(value) => value.parse().wrap_err_with(|| format!("Invalid {}", key)),
+        Err(_) => Ok(default),
+    }
+}
@@ -0,0 +1,230 @@
+use std::error::Error;
+
+use rocket::http::Status;
+use rocket::response::{self, Responder};
+use rocket::serde::json::{self, Json};
+use rocket::Request;
+use serde::Serialize;
+use tokio_postgres::error::SqlState;
+use utoipa::ToSchema;
+
+/// Errors returned by the API.
+///
+/// Every error is rendered as a JSON [`ErrorBody`] whose `code` is stable and can be
+/// matched on by clients, while `message` is meant for humans only.
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum ApiError {
+    /// A query parameter is missing or could not be parsed
+    #[error("invalid query parameters: {0}")]
+    InvalidParams(String),
+    /// The request body is larger than the `json` limit of the server
+    #[error("payload too large: {0}")]
+    PayloadTooLarge(String),
+    /// The network is not known to the service
+    #[error("unknown network: {0}")]
+    UnknownNetwork(String),
+    /// The requested block has not been proven on L1 yet, or not been indexed yet
+    #[error("no output is indexed for block {l2_block} on {network} yet")]
+    BlockNotIndexed {
+        network: String,
+        l2_block: i64,
+        highest_indexed_block: Option<i64>,
+    },
+    /// No output matches the block in an exact or preceding lookup
+    #[error("no output matches block {l2_block} on {network}")]
+    OutputNotFound { network: String, l2_block: i64 },
+    /// No output was proposed on L1 at or before the timestamp
+    #[error("no output of {network} was proposed on L1 at or before timestamp {l1_timestamp}")]
+    NoOutputBefore { network: String, l1_timestamp: i64 },
+    /// No dispute game with this index is indexed
+    #[error("dispute game {game_index} of {network} is not indexed")]
+    GameNotFound { network: String, game_index: i64 },
+    /// No webhook with this id is subscribed
+    #[error("webhook {0} does not exist")]
+    WebhookNotFound(String),
+    /// Nothing at all is indexed for the network yet
+    #[error("nothing is indexed for {0} yet")]
+    NetworkNotIndexed(String),
+    /// The API key is missing or unknown
+    #[error("unauthorized: {0}")]
+    Unauthorized(String),
+    /// The client sent more requests than its rate limit allows
+    #[error("rate limit of {client} exceeded, retry in {retry_after_sec}s")]
+    RateLimited {
+        client: String,
+        retry_after_sec: u64,
+    },
+    /// The client used up its daily quota
+    #[error("daily quota of {quota} requests of {client} exceeded, retry in {retry_after_sec}s")]
+    QuotaExceeded {
+        client: String,
+        quota: u64,
+        retry_after_sec: u64,
+    },
+    /// The L2 RPC could not be reached or failed to answer
+    #[error("L2 RPC unavailable: {0}")]
+    L2RpcUnavailable(String),
+    /// The database could not be reached or failed to answer
+    #[error("database unavailable: {0}")]
+    DatabaseUnavailable(String),
+    /// Anything that is a bug or a misconfiguration of the service
+    #[error("internal error: {0}")]
+    Internal(String),
+}
+
+/// The JSON body of every error response
+#[derive(Serialize, ToSchema, Clone, Debug)]
+pub struct ErrorBody {
+    /// Stable, machine readable error code
+    pub code: &'static str,
+    /// Human readable description of the error
+    pub message: String,
+    /// The highest indexed L2 block, set when the requested block is ahead of it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub highest_indexed_block: Option<i64>,
+}
+
+impl ApiError {
+    pub fn status(&self) -> Status {
+        match self {
+            ApiError::InvalidParams(_) => Status::UnprocessableEntity,
+            ApiError::PayloadTooLarge(_) => Status::PayloadTooLarge,
+            ApiError::UnknownNetwork(_) => Status::BadRequest,
+            ApiError::BlockNotIndexed { .. }
+            | ApiError::OutputNotFound { .. }
+            | ApiError::NoOutputBefore { .. }
+            | ApiError::GameNotFound { .. }
+            | ApiError::WebhookNotFound(_)
+            | ApiError::NetworkNotIndexed(_) => Status::NotFound,
+            ApiError::Unauthorized(_) => Status::Unauthorized,
+            ApiError::RateLimited { .. } | ApiError::QuotaExceeded { .. } => {
+                Status::TooManyRequests
+            }
+            ApiError::L2RpcUnavailable(_) => Status::BadGateway,
+            ApiError::DatabaseUnavailable(_) => Status::ServiceUnavailable,
+            ApiError::Internal(_) => Status::InternalServerError,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::InvalidParams(_) => "invalid_params",
+            ApiError::PayloadTooLarge(_) => "payload_too_large",
+            ApiError::UnknownNetwork(_) => "unknown_network",
+            ApiError::BlockNotIndexed { .. } => "block_not_indexed",
+            ApiError::OutputNotFound { .. } | ApiError::NoOutputBefore { .. } => "output_not_found",
+            ApiError::GameNotFound { .. } => "game_not_found",
+            ApiError::WebhookNotFound(_) => "webhook_not_found",
+            ApiError::NetworkNotIndexed(_) => "network_not_indexed",
+            ApiError::Unauthorized(_) => "unauthorized",
+            ApiError::RateLimited { .. } => "rate_limited",
+            ApiError::QuotaExceeded { .. } => "quota_exceeded",
+            ApiError::L2RpcUnavailable(_) => "l2_rpc_unavailable",
+            ApiError::DatabaseUnavailable(_) => "database_unavailable",
+            ApiError::Internal(_) => "internal_error",
+        }
+    }
+
+    pub fn body(&self) -> ErrorBody {
+        ErrorBody {
+            code: self.code(),
+            message: self.to_string(),
+            highest_indexed_block: match self {
+                ApiError::BlockNotIndexed {
+                    highest_indexed_block,
+                    ..
+                } => *highest_indexed_block,
+                _ => None,
+            },
+        }
+    }
+}
+
+/// A function that tells whether a PostgreSQL error comes from the database being out
+/// of reach, rather than from the query: a closed or failed connection, or the server
+/// refusing connections or shutting down.
+fn is_unavailable(e: &tokio_postgres::Error) -> bool {
+    match e.code() {
+        Some(code) => {
+            code.code().starts_with("08")
+                || [
+                    SqlState::ADMIN_SHUTDOWN,
+                    SqlState::CRASH_SHUTDOWN,
+                    SqlState::CANNOT_CONNECT_NOW,
+                    SqlState::TOO_MANY_CONNECTIONS,
+                ]
+                .contains(code)
+        }
+        None => {
+            e.is_closed()
+                || e.source()
+                    .is_some_and(|source| source.is::<std::io::Error>())
+        }
+    }
+}
+
+impl From<tokio_postgres::Error> for ApiError {
+    fn from(e: tokio_postgres::Error) -> Self {
+        if is_unavailable(&e) {
+            ApiError::DatabaseUnavailable(e.to_string())
+        } else {
+            ApiError::Internal(e.to_string())
+        }
+    }
+}
+
+impl From<deadpool_postgres::PoolError> for ApiError {
+    fn from(e: deadpool_postgres::PoolError) -> Self {
+        ApiError::DatabaseUnavailable(e.to_string())
+    }
+}
+
+impl From<json::Error<'_>> for ApiError {
+    fn from(e: json::Error<'_>) -> Self {
+        match e {
+            // Rocket stops reading the body at the limit and reports it as a truncated body
+            json::Error::Io(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                ApiError::PayloadTooLarge(e.to_string())
+            }
+            e => ApiError::InvalidParams(e.to_string()),
+        }
+    }
+}
+
+impl<'r> Responder<'r, 'static> for ApiError {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let status = self.status();
+        let mut response = (status, Json(self.body())).respond_to(request)?;
+        match &self {
+            ApiError::RateLimited {
+                retry_after_sec, ..
+            }
+            | ApiError::QuotaExceeded {
+                retry_after_sec, ..
+            } => response.set_raw_header("Retry-After", retry_after_sec.to_string()),
+            _ => false,
+        };
+        Ok(response)
+    }
+}
+
+/// Renders Rocket's own errors (unknown route, unparsable request, panics) with the
+/// same JSON schema as [`ApiError`]
+#[catch(default)]
+pub fn default_catcher(status: Status, _request: &Request) -> (Status, Json<ErrorBody>) {
+    let code = match status.code {
+        404 => "not_found",
+        413 => "payload_too_large",
+        422 => "invalid_params",
+        400..=499 => "bad_request",
+        _ => "internal_error",
+    };
+    (
+        status,
+        Json(ErrorBody {
+            code,
+            message: status.reason_lossy().to_string(),
+            highest_indexed_block: None,
+        }),
+    )
+}
@@ -0,0 +1,275 @@
+use common::{Network, NetworkConfig};
+use rocket::FromFormField;
+use serde::Serialize;
+use tokio_postgres::types::ToSql;
+use tokio_postgres::Row;
+use tracing::instrument;
+use utoipa::ToSchema;
+
+use crate::error::ApiError;
+use crate::queries::{not_indexed, trusted_games_filter, trusted_proposer, uses_fdg};
+
+/// The resolution status of a dispute game, stored as `game_state`
+#[derive(FromFormField, Serialize, ToSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GameStatus {
+    #[field(value = "in_progress")]
+    InProgress,
+    #[field(value = "challenger_wins")]
+    ChallengerWins,
+    #[field(value = "defender_wins")]
+    DefenderWins,
+}
+
+impl GameStatus {
+    fn game_state(self) -> i64 {
+        match self {
+            GameStatus::InProgress => 0,
+            GameStatus::ChallengerWins => 1,
+            GameStatus::DefenderWins => 2,
+        }
+    }
+
+    pub fn from_game_state(game_state: i64) -> Option<Self> {
+        match game_state {
+            0 => Some(GameStatus::InProgress),
+            1 => Some(GameStatus::ChallengerWins),
+            2 => Some(GameStatus::DefenderWins),
+            _ => None,
+        }
+    }
+}
+
+/// A dispute game as stored by the indexer, whatever its status or proposer
+#[derive(Serialize, ToSchema, Clone, Debug)]
+pub struct DisputeGameDetail {
+    pub network: String,
+    pub game_index: i64,
+    pub game_address: String,
+    pub game_type: i64,
+    /// 0: IN_PROGRESS, 1: CHALLENGER_WINS, 2: DEFENDER_WINS
+    pub game_state: i64,
+    /// `game_state` by name, unset for an unknown state
+    pub status: Option<GameStatus>,
+    pub proposer_address: String,
+    pub root_claim: String,
+    pub l2_block_number: i64,
+    /// Unset when the L2 block could not be fetched, e.g. for a game on a nonexistent block
+    pub l2_state_root: Option<String>,
+    pub l2_withdrawal_storage_root: Option<String>,
+    pub l2_block_hash: Option<String>,
+    /// Whether the root claim matches the output root of the L2 block, unset when it
+    /// could not be checked
+    pub claim_verified: Option<bool>,
+    /// Whether the API serves this game's claim as the output of its L2 block
+    pub trusted: bool,
+    /// The L1 timestamp the game was created at
+    pub created_at: i64,
+    pub l1_transaction_hash: String,
+    pub l1_block_number: i64,
+    pub l1_transaction_index: i64,
+    pub l1_block_hash: String,
+}
+
+impl DisputeGameDetail {
+    fn from_row(network: &Network, trusted_proposer: &str, row: &Row) -> Self {
+        let game_state: i64 = row.get("game_state");
+        let proposer_address: String = row.get("proposer_address");
+        let l2_state_root: Option<String> = row.get("l2_state_root");
+        // Mirrors the trusted games filter of the output queries
+        let trusted = l2_state_root.is_some()
+            && (game_state == 2 || (proposer_address == trusted_proposer && game_state == 0));
+
+        DisputeGameDetail {
+            network: network.to_string(),
+            game_index: row.get("game_index"),
+            game_address: row.get("game_address"),
+            game_type: row.get("game_type"),
+            game_state,
+            status: GameStatus::from_game_state(game_state),
+            proposer_address,
+            root_claim: row.get("root_claim"),
+            l2_block_number: row.get("l2_block_number"),
+            l2_state_root,
+            l2_withdrawal_storage_root: row.get("l2_withdrawal_storage_root"),
+            l2_block_hash: row.get("l2_block_hash"),
+            claim_verified: row.get("claim_verified"),
+            trusted,
+            created_at: row.get("timestamp"),
+            l1_transaction_hash: row.get("l1_transaction_hash"),
+            l1_block_number: row.get("l1_block_number"),
+            l1_transaction_index: row.get("l1_transaction_index"),
+            l1_block_hash: row.get("l1_block_hash"),
+        }
+    }
+}
+
+/// Filters of a dispute game listing, unset filters match every game
+#[derive(Default, Debug)]
+pub struct GameFilter {
+    pub status: Option<GameStatus>,
+    pub game_type: Option<i64>,
+    pub proposer: Option<String>,
+    pub from_l2_block: Option<i64>,
+    pub to_l2_block: Option<i64>,
+    pub claim_verified: Option<bool>,
+}
+
+const GAME_COLUMNS: &str = "game_index, game_address, game_type, timestamp, root_claim, game_state, proposer_address, l2_block_number, l2_state_root, l2_withdrawal_storage_root, l2_block_hash, claim_verified, l1_transaction_hash, l1_block_number, l1_transaction_index, l1_block_hash";
+
+/// A function that returns the dispute games table of a network, if it has one
+fn games_table(network: &Network, network_config: &NetworkConfig) -> Result<String, ApiError> {
+    if !uses_fdg(network) {
+        return Err(ApiError::InvalidParams(format!(
+            "{} has no dispute games",
+            network
+        )));
+    }
+    Ok(format!("{}_fault_dispute_games", network_config.name))
+}
+
+/// A function that lists the dispute games of a network, newest first.
+/// Parameters:
+/// * network: The network
+/// * network_config: The network configuration
+/// * filter: The filters the games must match
+/// * before: Only games with a lower game index are listed, used for paging
+/// * limit: The maximum number of games to return
+/// * pg_client: The postgres client
+///
+/// Returns:
+/// * The matching games, ordered by descending game index
+#[instrument(name = "db.query", skip_all, fields(db.system = "postgresql", network = %network, limit))]
+pub async fn list_dispute_games(
+    network: &Network,
+    network_config: &NetworkConfig,
+    filter: &GameFilter,
+    before: Option<i64>,
+    limit: i64,
+    pg_client: &tokio_postgres::Client,
+) -> Result<Vec<DisputeGameDetail>, ApiError> {
+    let table_name = games_table(network, network_config)?;
+    let trusted_proposer = trusted_proposer(network, network_config)?;
+
+    let game_state = filter.status.map(GameStatus::game_state);
+    let proposer = filter.proposer.as_ref().map(|p| p.to_lowercase());
+    let mut conditions = Vec::new();
+    let mut params: Vec<&(dyn ToSql + Sync)> = vec![&limit];
+    let optional_conditions: [(&str, Option<&(dyn ToSql + Sync)>); 7] = [
+        ("game_index < {}", before.as_ref().map(|v| v as _)),
+        ("game_state = {}", game_state.as_ref().map(|v| v as _)),
+        ("game_type = {}", filter.game_type.as_ref().map(|v| v as _)),
+        ("proposer_address = {}", proposer.as_ref().map(|v| v as _)),
+        (
+            "l2_block_number >= {}",
+            filter.from_l2_block.as_ref().map(|v| v as _),
+        ),
+        (
+            "l2_block_number <= {}",
+            filter.to_l2_block.as_ref().map(|v| v as _),
+        ),
+        (
+            "claim_verified = {}",
+            filter.claim_verified.as_ref().map(|v| v as _),
+        ),
+    ];
+    for (condition, value) in optional_conditions {
+        if let Some(value) = value {
+            params.push(value);
+            conditions.push(condition.replace("{}", &format!("${}", params.len())));
+        }
+    }
+
+    let select_query = format!(
+        "SELECT {}
+    FROM {}
+    {}
+    ORDER BY game_index DESC
+    LIMIT $1;",
+        GAME_COLUMNS,
+        table_name,
+        if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        }
+    );
+
+    let rows = pg_client
+        .query(&select_query, &params)
+        .await
+        .map_err(not_indexed(network))?;
+    Ok(rows
+        .iter()
+        .map(|row| DisputeGameDetail::from_row(network, &trusted_proposer, row))
+        .collect())
+}
+
+/// A function that returns a dispute game by its index in the DisputeGameFactory
+#[instrument(name = "db.query", skip_all, fields(db.system = "postgresql", network = %network, game_index))]
+pub async fn get_dispute_game(
+    network: &Network,
+    network_config: &NetworkConfig,
+    game_index: i64,
+    pg_client: &tokio_postgres::Client,
+) -> Result<DisputeGameDetail, ApiError> {
+    let table_name = games_table(network, network_config)?;
+    let trusted_proposer = trusted_proposer(network, network_config)?;
+
+    let select_query = format!(
+        "SELECT {}
+    FROM {}
+    WHERE game_index = $1
+    ORDER BY id ASC
+    LIMIT 1;",
+        GAME_COLUMNS, table_name
+    );
+
+    pg_client
+        .query_opt(&select_query, &[&game_index])
+        .await
+        .map_err(not_indexed(network))?
+        .map(|row| DisputeGameDetail::from_row(network, &trusted_proposer, &row))
+        .ok_or_else(|| ApiError::GameNotFound {
+            network: network.to_string(),
+            game_index,
+        })
+}
+
+/// A function that returns the games the output routes trust among the most recent
+/// games of a network.
+/// Parameters:
+/// * network: The network
+/// * network_config: The network configuration
+/// * window: How many of the highest game indexes are considered
+/// * pg_client: The postgres client
+///
+/// Returns:
+/// * The indexes of the trusted games, ascending
+#[instrument(name = "db.query", skip_all, fields(db.system = "postgresql", network = %network, window))]
+pub async fn list_trusted_game_indexes(
+    network: &Network,
+    network_config: &NetworkConfig,
+    window: i64,
+    pg_client: &tokio_postgres::Client,
+) -> Result<Vec<i64>, ApiError> {
+    let table_name = games_table(network, network_config)?;
+    let trusted_proposer = trusted_proposer(network, network_config)?;
+
+    let select_query = format!(
+        "SELECT game_index
+    FROM {}
+    WHERE game_index > (SELECT COALESCE(MAX(game_index), 0) FROM {}) - $2
+    AND {}
+    ORDER BY game_index ASC;",
+        table_name,
+        table_name,
+        trusted_games_filter(1)
+    );
+
+    let rows = pg_client
+        .query(&select_query, &[&trusted_proposer, &window])
+        .await
+        .map_err(not_indexed(network))?;
+    Ok(rows.iter().map(|row| row.get(0)).collect())
+}
@@ -0,0 +1,149 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use ::metrics::gauge;
+use common::{LiveEvent, LIVE_EVENTS_CHANNEL};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::{Orbit, Rocket};
+use tokio::sync::{broadcast, mpsc};
+use tokio_postgres::{AsyncMessage, NoTls};
+use tracing::{debug, info, warn};
+
+use crate::cache::OutputCache;
+
+/// Events kept for the clients that read slower than they are published
+const BUFFERED_EVENTS: usize = 1024;
+/// The delay before reconnecting to the database, doubled after every failure up to
+/// `MAX_RECONNECT_DELAY`
+const FIRST_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// Listens to the events the indexer publishes with `pg_notify`, invalidates the output
+/// cache with them and broadcasts them to the `/v1/events` clients. The listening
+/// connection is reopened when it drops, and what was published in the meantime is lost.
+#[derive(Clone)]
+pub struct LiveFeed {
+    db_url: String,
+    sender: broadcast::Sender<Arc<LiveEvent>>,
+    cache: OutputCache,
+}
+
+impl LiveFeed {
+    pub fn from_env(cache: OutputCache) -> eyre::Result<Self> {
+        let db_url = std::env::var("DB_URL").map_err(|_| eyre::eyre!("DB_URL must be set"))?;
+        let (sender, _) = broadcast::channel(BUFFERED_EVENTS);
+        Ok(Self {
+            db_url,
+            sender,
+            cache,
+        })
+    }
+
+    /// A function that returns a receiver of the events published from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<LiveEvent>> {
+        self.sender.subscribe()
+    }
+
+    async fn run(self) {
+        let mut delay = FIRST_RECONNECT_DELAY;
+        loop {
+            let mut listening = false;
+            if let Err(e) = self.listen(&mut listening).await {
+                warn!(error = %e, "Lost the indexer events connection");
+            }
+            self.cache.disable();
+            if listening {
+                delay = FIRST_RECONNECT_DELAY;
+            }
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+        }
+    }
+
+    /// Opens a connection, listens to the channel and broadcasts the notifications
+    /// until the connection drops
+    async fn listen(&self, listening: &mut bool) -> Result<(), tokio_postgres::Error> {
+        let (client, mut connection) = tokio_postgres::connect(&self.db_url, NoTls).await?;
+
+        // Notifications come in through the connection, which also has to be polled
+        // for LISTEN to be answered
+        let (notifications, mut received) = mpsc::unbounded_channel();
+        let driver = tokio::spawn(async move {
+            loop {
+                match std::future::poll_fn(|cx| connection.poll_message(cx)).await {
+                    Some(Ok(AsyncMessage::Notification(notification))) => {
+                        let _ = notifications.send(notification);
+                    }
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => return Err(e),
+                    None => return Ok(()),
+                }
+            }
+        });
+
+        client
+            .batch_execute(&format!("LISTEN {}", LIVE_EVENTS_CHANNEL))
+            .await?;
+        *listening = true;
+        self.cache.enable();
+        info!(
+            channel = LIVE_EVENTS_CHANNEL,
+            "Listening to the indexer events"
+        );
+
+        while let Some(notification) = received.recv().await {
+            match serde_json::from_str::<LiveEvent>(notification.payload()) {
+                Ok(event) => {
+                    debug!(event_id = %event.id, "New live event");
+                    self.cache.invalidate(&event);
+                    // Fails when nobody is streaming, which is fine
+                    let _ = self.sender.send(Arc::new(event));
+                }
+                Err(e) => warn!(
+                    error = %e,
+                    payload = notification.payload(),
+                    "Ignored an invalid indexer event"
+                ),
+            }
+        }
+
+        drop(client);
+        match driver.await {
+            Ok(result) => result,
+            Err(e) => {
+                warn!(error = %e, "The indexer events connection task failed");
+                Ok(())
+            }
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for LiveFeed {
+    fn info(&self) -> Info {
+        Info {
+            name: "Live events feed",
+            kind: Kind::Liftoff,
+        }
+    }
+
+    async fn on_liftoff(&self, _rocket: &Rocket<Orbit>) {
+        tokio::spawn(self.clone().run());
+    }
+}
+
+/// Counts the open `/v1/events` streams while it is alive
+pub struct StreamGuard;
+
+impl StreamGuard {
+    pub fn open() -> Self {
+        gauge!("live_event_streams").increment(1.0);
+        StreamGuard
+    }
+}
+
+impl Drop for StreamGuard {
+    fn drop(&mut self) {
+        gauge!("live_event_streams").decrement(1.0);
+    }
+}
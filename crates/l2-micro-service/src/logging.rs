@@ -0,0 +1,183 @@
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{Header, HeaderMap};
+use rocket::route::{Handler, Outcome};
+use rocket::{Data, Request, Response, Route};
+use tracing::{debug, error, field, info, info_span, warn, Instrument, Span};
+
+use crate::telemetry;
+
+/// The header carrying the request id, read from the request and echoed in the response
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+/// Longest request id accepted from a client, longer ones are replaced
+const MAX_REQUEST_ID_LEN: usize = 128;
+/// Routes polled by the Kubernetes probes, only logged at debug level when they succeed
+const PROBE_ROUTES: &[&str] = &["/healthz", "/readyz"];
+/// Headers whose value is never logged
+const REDACTED_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+    "x-api-key",
+];
+
+/// The id and span of a request, created once per request by [`TracingFairing`]
+struct RequestSpan {
+    request_id: String,
+    span: Span,
+}
+
+fn request_span<'a>(request: &'a Request<'_>) -> &'a RequestSpan {
+    request.local_cache(|| RequestSpan {
+        request_id: String::new(),
+        span: Span::none(),
+    })
+}
+
+/// A function that reuses the request id sent by a proxy or client, or generates one
+fn incoming_request_id(request: &Request<'_>) -> String {
+    request
+        .headers()
+        .get_one(REQUEST_ID_HEADER)
+        .filter(|id| {
+            !id.is_empty()
+                && id.len() <= MAX_REQUEST_ID_LEN
+                && id.chars().all(|c| c.is_ascii_graphic())
+        })
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+}
+
+/// Logs every request under an `http_request` span carrying its request id, method and
+/// URI, and returns the request id in `X-Request-Id`. The span continues the trace of
+/// the W3C `traceparent` header when spans are exported.
+///
+/// Headers are only logged when `LOG_HEADERS=true`, with the credentials and
+/// `LOG_REDACTED_HEADERS` (comma separated) redacted.
+pub struct TracingFairing {
+    log_headers: bool,
+    redacted_headers: Vec<String>,
+}
+
+impl TracingFairing {
+    pub fn from_env() -> Self {
+        let log_headers = std::env::var("LOG_HEADERS")
+            .map(|value| value.eq_ignore_ascii_case("true") || value == "1")
+            .unwrap_or(false);
+        let redacted_headers = REDACTED_HEADERS
+            .iter()
+            .map(|name| name.to_string())
+            .chain(
+                std::env::var("LOG_REDACTED_HEADERS")
+                    .unwrap_or_default()
+                    .split(',')
+                    .map(|name| name.trim().to_lowercase())
+                    .filter(|name| !name.is_empty()),
+            )
+            .collect();
+
+        Self {
+            log_headers,
+            redacted_headers,
+        }
+    }
+
+    fn log_headers(&self, direction: &'static str, headers: &HeaderMap<'_>) {
+        if !self.log_headers {
+            return;
+        }
+        for header in headers.iter() {
+            let name = header.name().as_str().to_lowercase();
+            let value = if self.redacted_headers.contains(&name) {
+                "[redacted]"
+            } else {
+                header.value()
+            };
+            info!(direction, header = %name, value, "Header");
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for TracingFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "Request tracing",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+        let request_id = incoming_request_id(request);
+        let span = info_span!(
+            "http_request",
+            request_id = %request_id,
+            method = %request.method(),
+            uri = %request.uri(),
+            otel.kind = "server",
+            trace_id = field::Empty,
+            api_client = field::Empty
+        );
+        common::set_remote_parent(&span, |name| request.headers().get_one(name));
+        span.record("trace_id", common::trace_id(&span));
+        span.in_scope(|| {
+            debug!("Request started");
+            self.log_headers("request", request.headers());
+        });
+        request.local_cache(|| RequestSpan { request_id, span });
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let RequestSpan { request_id, span } = request_span(request);
+        let status = response.status().code;
+        let duration_ms = telemetry::request_duration(request).as_millis() as u64;
+        let route = request.route().map(|route| route.uri.path().to_string());
+
+        if !request_id.is_empty() {
+            response.set_header(Header::new(REQUEST_ID_HEADER, request_id.clone()));
+        }
+        span.in_scope(|| {
+            match status {
+                500.. => error!(status, duration_ms, route, "Request failed"),
+                400..=499 => warn!(status, duration_ms, route, "Request rejected"),
+                _ if route
+                    .as_deref()
+                    .is_some_and(|route| PROBE_ROUTES.contains(&route)) =>
+                {
+                    debug!(status, duration_ms, route, "Request completed")
+                }
+                _ => info!(status, duration_ms, route, "Request completed"),
+            }
+            self.log_headers("response", response.headers());
+        });
+    }
+}
+
+/// Runs a route handler inside the span of its request, so that the lines it logs
+/// carry the request id
+#[derive(Clone)]
+struct Traced(Box<dyn Handler>);
+
+#[rocket::async_trait]
+impl Handler for Traced {
+    async fn handle<'r>(&self, request: &'r Request<'_>, data: Data<'r>) -> Outcome<'r> {
+        let span = request_span(request).span.clone();
+        self.0.handle(request, data).instrument(span).await
+    }
+}
+
+/// A function that wraps the handlers of some routes in the span of their request.
+/// Parameters:
+/// * routes: The routes to mount
+///
+/// Returns:
+/// * The same routes, logging under the `http_request` span
+pub fn traced(routes: Vec<Route>) -> Vec<Route> {
+    routes
+        .into_iter()
+        .map(|mut route| {
+            route.handler = Box::new(Traced(route.handler));
+            route
+        })
+        .collect()
+}
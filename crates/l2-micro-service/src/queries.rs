@@ -0,0 +1,893 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use common::{get_network_config, ChainName, ChainType, Network, NetworkConfig, OUTPUT_VERSION_V0};
+use rocket::FromFormField;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::error::SqlState;
+use tokio_postgres::Row;
+use tracing::{debug, instrument, warn};
+use utoipa::ToSchema;
+
+use crate::error::ApiError;
+
+/// The output of a network, as stored by the indexer
+#[derive(Serialize, Clone, Debug)]
+pub enum OutputType {
+    OpStack(OPStackParamsOutput),
+    OpStackFDG(OPStackFaultDisputeGameOutput),
+    Arbitrum(ArbitrumParamsOutput),
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct OPStackFaultDisputeGameOutput {
+    pub game_index: i64,
+    pub game_address: String,
+    pub game_type: i64,
+    pub timestamp: i64,
+    pub root_claim: String,
+    pub game_state: i64,
+    pub proposer_address: String,
+    pub l2_block_number: i64,
+    /// Only unset for untrusted games, which lookups return as well
+    pub l2_state_root: Option<String>,
+    pub l2_withdrawal_storage_root: Option<String>,
+    pub l2_block_hash: Option<String>,
+    pub l1_transaction_hash: String,
+    pub l1_block_number: i64,
+    pub l1_transaction_index: i64,
+    pub l1_block_hash: String,
+    pub version_byte: String,
+}
+
+// Output for request parameters of opstack
+#[derive(Serialize, Clone, Debug)]
+pub struct OPStackParamsOutput {
+    pub l2_output_root: String,
+    pub l2_output_index: i32,
+    pub l2_block_number: i32,
+    pub l1_timestamp: i32,
+    pub l1_transaction_hash: String,
+    pub l1_block_number: i32,
+    pub l1_transaction_index: i32,
+    pub l1_block_hash: String,
+    /// The output root preimage, only served by the v1 routes
+    #[serde(skip)]
+    pub l2_state_root: Option<String>,
+    #[serde(skip)]
+    pub l2_withdrawal_storage_root: Option<String>,
+    #[serde(skip)]
+    pub l2_block_hash: Option<String>,
+}
+
+// Output for request parameters of arbitrum
+#[derive(Serialize, Clone, Debug)]
+pub struct ArbitrumParamsOutput {
+    pub l2_output_root: String,
+    pub l2_block_hash: String,
+    pub l2_block_number: i32,
+    pub l1_transaction_hash: String,
+    pub l1_block_number: i32,
+    pub l1_transaction_index: i32,
+    pub l1_block_hash: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct HighestBlock {
+    pub chain: String,
+    pub block_number: i32,
+}
+
+impl OPStackParamsOutput {
+    fn from_row(row: &Row) -> Self {
+        Self {
+            l2_output_root: row.get("l2_output_root"),
+            l2_output_index: row.get("l2_output_index"),
+            l2_block_number: row.get("l2_block_number"),
+            l1_timestamp: row.get("l1_timestamp"),
+            l1_transaction_hash: row.get("l1_transaction_hash"),
+            l1_block_number: row.get("l1_block_number"),
+            l1_transaction_index: row.get("l1_transaction_index"),
+            l1_block_hash: row.get("l1_block_hash"),
+            l2_state_root: row.get("l2_state_root"),
+            l2_withdrawal_storage_root: row.get("l2_withdrawal_storage_root"),
+            l2_block_hash: row.get("l2_block_hash"),
+        }
+    }
+}
+
+impl OPStackFaultDisputeGameOutput {
+    fn from_row(row: &Row) -> Self {
+        Self {
+            game_index: row.get("game_index"),
+            game_address: row.get("game_address"),
+            game_type: row.get("game_type"),
+            timestamp: row.get("timestamp"),
+            root_claim: row.get("root_claim"),
+            game_state: row.get("game_state"),
+            proposer_address: row.get("proposer_address"),
+            l2_block_number: row.get("l2_block_number"),
+            l2_state_root: row.get("l2_state_root"),
+            l2_withdrawal_storage_root: row.get("l2_withdrawal_storage_root"),
+            l2_block_hash: row.get("l2_block_hash"),
+            l1_transaction_hash: row.get("l1_transaction_hash"),
+            l1_block_number: row.get("l1_block_number"),
+            l1_transaction_index: row.get("l1_transaction_index"),
+            l1_block_hash: row.get("l1_block_hash"),
+            version_byte: OUTPUT_VERSION_V0.to_string(),
+        }
+    }
+}
+
+impl ArbitrumParamsOutput {
+    fn from_row(row: &Row) -> Self {
+        Self {
+            l2_output_root: row.get("l2_output_root"),
+            l2_block_hash: row.get("l2_block_hash"),
+            l2_block_number: row.get("l2_block_number"),
+            l1_transaction_hash: row.get("l1_transaction_hash"),
+            l1_block_number: row.get("l1_block_number"),
+            l1_transaction_index: row.get("l1_transaction_index"),
+            l1_block_hash: row.get("l1_block_hash"),
+        }
+    }
+}
+
+/// A function that resolves a network name to the network and its config
+pub fn parse_network(network: &str) -> Result<(Network, NetworkConfig), ApiError> {
+    let network =
+        Network::from_str(network).map_err(|_| ApiError::UnknownNetwork(network.to_string()))?;
+    let network_config = get_network_config(network.chain_type, network.chain_name)
+        .map_err(|e| ApiError::UnknownNetwork(e.to_string()))?;
+    Ok((network, network_config))
+}
+
+/// A function that returns the trusted proposer of an FDG network
+pub fn trusted_proposer(
+    network: &Network,
+    network_config: &NetworkConfig,
+) -> Result<String, ApiError> {
+    network_config
+        .trusted_proposer_address
+        .clone()
+        .ok_or_else(|| {
+            ApiError::Internal(format!(
+                "trusted_proposer_address is not configured for {}",
+                network
+            ))
+        })
+}
+
+/// Whether the network settles through the Arbitrum Outbox
+pub fn is_arbitrum_stack(network: &Network) -> bool {
+    matches!(
+        network.chain_name,
+        ChainName::Arbitrum | ChainName::ApeChain
+    )
+}
+
+/// Whether the indexer follows the dispute game factory of the network
+pub fn uses_fdg(network: &Network) -> bool {
+    matches!(network.chain_name, ChainName::Optimism | ChainName::Base)
+        && matches!(network.chain_type, ChainType::Mainnet | ChainType::Sepolia)
+}
+
+/// A function that returns the last L2 block proven by the legacy output oracle of
+/// an FDG network
+fn dispute_game_transition(
+    network: &Network,
+    network_config: &NetworkConfig,
+) -> Result<u64, ApiError> {
+    network_config
+        .transition_to_dispute_game_system_l2_block
+        .ok_or_else(|| {
+            ApiError::Internal(format!(
+                "transition_to_dispute_game_system_l2_block is not configured for {}",
+                network
+            ))
+        })
+}
+
+/// The filter selecting the dispute games whose root claim can be trusted: resolved in
+/// favor of the defender, or still in progress but proposed by the trusted proposer
+/// of the network, bound as parameter `$param`
+pub fn trusted_games_filter(param: usize) -> String {
+    format!(
+        "(
+                  game_state = 2
+                  OR (proposer_address = ${} AND game_state IN (0, 2))
+                 )
+           AND l2_state_root IS NOT NULL",
+        param
+    )
+}
+
+/// A function that maps the errors of the queries on the output tables of a network. A
+/// table that does not exist yet means nothing is indexed for the network.
+/// Parameters:
+/// * network: The network the tables belong to
+pub fn not_indexed(network: &Network) -> impl Fn(tokio_postgres::Error) -> ApiError + '_ {
+    move |e| {
+        if e.code() == Some(&SqlState::UNDEFINED_TABLE) {
+            ApiError::NetworkNotIndexed(network.to_string())
+        } else {
+            e.into()
+        }
+    }
+}
+
+/// A function that returns the highest L2 block with an indexed output.
+/// For FDG networks both the legacy outputs before the transition and the trusted
+/// dispute games after it are considered.
+#[instrument(name = "db.query", skip_all, fields(db.system = "postgresql", network = %network))]
+pub async fn get_highest_l2_block(
+    network: &Network,
+    network_config: &NetworkConfig,
+    pg_client: &tokio_postgres::Client,
+) -> Result<HighestBlock, ApiError> {
+    let mut max_block_number: Option<i32> = None;
+
+    if uses_fdg(network) {
+        // For FDG-enabled networks, we need to check both tables
+        // First, get the transition block
+        let transition_block = dispute_game_transition(network, network_config)? as i64;
+        let trusted_proposer = trusted_proposer(network, network_config)?;
+
+        // Query the standard table for blocks before the transition
+        let standard_query = format!(
+            "SELECT max(blocks.l2_block_number) FROM public.{} blocks WHERE blocks.l2_block_number <= $1::BIGINT",
+            network
+        );
+
+        let standard_rows = pg_client
+            .query(&standard_query, &[&transition_block])
+            .await
+            .map_err(not_indexed(network))?;
+        if !standard_rows.is_empty() {
+            let block_number: Option<i32> = standard_rows[0].try_get(0)?;
+            if let Some(block_num) = block_number {
+                max_block_number = Some(block_num);
+                debug!(block_num, "Standard table max block (before transition)");
+            }
+        }
+
+        // Query the FDG table for blocks after the transition
+        let fdg_query = format!(
+            "SELECT max(l2_block_number) FROM public.{}_fault_dispute_games
+           WHERE l2_block_number > $1
+           AND {}",
+            network,
+            trusted_games_filter(2)
+        );
+
+        match pg_client
+            .query(&fdg_query, &[&transition_block, &trusted_proposer])
+            .await
+        {
+            Ok(fdg_rows) => {
+                if !fdg_rows.is_empty() {
+                    let fdg_block_number: Option<i64> = fdg_rows[0].try_get(0)?;
+                    if let Some(fdg_block_num) = fdg_block_number {
+                        // Convert i64 to i32 for consistency
+                        let fdg_block_num_i32 = fdg_block_num as i32;
+                        debug!(
+                            block_num = fdg_block_num_i32,
+                            "FDG table max block (after transition)"
+                        );
+                        // Update max_block_number if FDG table has a higher block
+                        max_block_number = match max_block_number {
+                            Some(current_max) => {
+                                Some(std::cmp::max(current_max, fdg_block_num_i32))
+                            }
+                            None => Some(fdg_block_num_i32),
+                        };
+                    }
+                }
+            }
+            Err(e) => {
+                // FDG table might not exist yet, log but don't fail
+                warn!(error = %e, "FDG table query failed (table may not exist yet)");
+            }
+        }
+    } else {
+        // For non-FDG networks, just query the standard table
+        let standard_query = format!(
+            "SELECT max(blocks.l2_block_number) FROM public.{} blocks",
+            network
+        );
+
+        let standard_rows = pg_client
+            .query(&standard_query, &[])
+            .await
+            .map_err(not_indexed(network))?;
+        if !standard_rows.is_empty() {
+            let block_number: Option<i32> = standard_rows[0].try_get(0)?;
+            if let Some(block_num) = block_number {
+                max_block_number = Some(block_num);
+                debug!(block_num, "Standard table max block");
+            }
+        }
+    }
+
+    // Return the highest block number found
+    match max_block_number {
+        Some(block_number) => {
+            debug!(block_number, "Final max block number");
+            Ok(HighestBlock {
+                chain: network.to_string(),
+                block_number,
+            })
+        }
+        None => Err(ApiError::NetworkNotIndexed(network.to_string())),
+    }
+}
+
+/// How an output is matched to a requested L2 block
+#[derive(
+    FromFormField,
+    Deserialize,
+    ToSchema,
+    Clone,
+    Copy,
+    Default,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum LookupMode {
+    /// The first output at or after the block, i.e. the output proving it
+    #[default]
+    #[field(value = "covering")]
+    Covering,
+    /// Only an output at exactly the block
+    #[field(value = "exact")]
+    Exact,
+    /// The last output at or before the block
+    #[field(value = "preceding")]
+    Preceding,
+}
+
+impl LookupMode {
+    /// The condition on `l2_block_number` and the order picking the matching row
+    fn condition(&self) -> (&'static str, &'static str) {
+        match self {
+            LookupMode::Covering => (">=", "ASC"),
+            LookupMode::Exact => ("=", "ASC"),
+            LookupMode::Preceding => ("<=", "DESC"),
+        }
+    }
+}
+
+/// The L2 block or L1 timestamp range of a listing, bounds included
+#[derive(Clone, Copy, Debug)]
+pub enum OutputRange {
+    L2Blocks { from: i64, to: i64 },
+    Timestamps { from: i64, to: i64 },
+}
+
+/// The tables an output can be stored in
+#[derive(Clone, Copy, Debug)]
+enum OutputTable {
+    OpStack,
+    FaultDisputeGames,
+    Arbitrum,
+}
+
+impl OutputTable {
+    fn name(&self, network: &Network) -> String {
+        match self {
+            OutputTable::FaultDisputeGames => format!("{}_fault_dispute_games", network),
+            OutputTable::OpStack | OutputTable::Arbitrum => network.to_string(),
+        }
+    }
+
+    fn columns(&self) -> &'static str {
+        match self {
+            OutputTable::OpStack => "l2_output_root, l2_output_index, l2_block_number, l1_timestamp, l1_transaction_hash, l1_block_number, l1_transaction_index, l1_block_hash, l2_state_root, l2_withdrawal_storage_root, l2_block_hash",
+            OutputTable::FaultDisputeGames => "game_index, game_address, game_type, timestamp, root_claim, game_state, proposer_address, l2_block_number, l2_state_root, l2_withdrawal_storage_root, l2_block_hash, l1_transaction_hash, l1_block_number, l1_transaction_index, l1_block_hash",
+            OutputTable::Arbitrum => "l2_output_root, l2_block_hash, l2_block_number, l1_transaction_hash, l1_block_number, l1_transaction_index, l1_block_hash",
+        }
+    }
+
+    /// The column holding when an output was proposed on L1. Dispute games indexed by
+    /// earlier versions have `l1_timestamp` set to 0 until the indexer backfills it,
+    /// while `timestamp`, the `createdAt()` of the game, is the same value and was
+    /// always stored.
+    fn l1_timestamp_column(&self) -> &'static str {
+        match self {
+            OutputTable::FaultDisputeGames => "timestamp",
+            OutputTable::OpStack | OutputTable::Arbitrum => "l1_timestamp",
+        }
+    }
+
+    /// Only trusted dispute games are outputs, the filter binds the trusted proposer
+    /// as parameter `$param`
+    fn filter(&self, param: usize) -> String {
+        match self {
+            OutputTable::FaultDisputeGames => trusted_games_filter(param),
+            OutputTable::OpStack | OutputTable::Arbitrum => "TRUE".to_string(),
+        }
+    }
+
+    fn output_from_row(&self, row: &Row) -> OutputType {
+        match self {
+            OutputTable::OpStack => OutputType::OpStack(OPStackParamsOutput::from_row(row)),
+            OutputTable::FaultDisputeGames => {
+                OutputType::OpStackFDG(OPStackFaultDisputeGameOutput::from_row(row))
+            }
+            OutputTable::Arbitrum => OutputType::Arbitrum(ArbitrumParamsOutput::from_row(row)),
+        }
+    }
+}
+
+/// The tables holding the outputs of a network, with the L2 block range each one covers
+fn output_tables(
+    network: &Network,
+    network_config: &NetworkConfig,
+) -> Vec<(OutputTable, std::ops::RangeInclusive<i64>)> {
+    if is_arbitrum_stack(network) {
+        return vec![(OutputTable::Arbitrum, 0..=i64::MAX)];
+    }
+    // Networks without a dispute game transition only have legacy outputs
+    match network_config.transition_to_dispute_game_system_l2_block {
+        Some(transition_block) if uses_fdg(network) => {
+            let transition_block = transition_block as i64;
+            vec![
+                (OutputTable::OpStack, 0..=transition_block),
+                (
+                    OutputTable::FaultDisputeGames,
+                    transition_block + 1..=i64::MAX,
+                ),
+            ]
+        }
+        _ => vec![(OutputTable::OpStack, 0..=i64::MAX)],
+    }
+}
+
+/// A function that gets the output matching an L2 block, picking the right table for
+/// the network and block.
+/// Parameters:
+/// * network: The network to query
+/// * network_config: The config of the network
+/// * l2_block: The L2 block number
+/// * mode: How the output is matched to the block
+/// * pg_client: The postgres client
+///
+/// Returns:
+/// * The output, or `BlockNotIndexed` with the highest indexed block if there is none yet
+pub async fn get_output_root(
+    network: &Network,
+    network_config: &NetworkConfig,
+    l2_block: i64,
+    mode: LookupMode,
+    pg_client: &tokio_postgres::Client,
+) -> Result<OutputType, ApiError> {
+    let output = get_output_roots(network, network_config, &[l2_block], mode, pg_client)
+        .await?
+        .remove(&l2_block);
+
+    match output {
+        Some(output) => {
+            debug!(network = %network, l2_block, ?output, "Found output");
+            Ok(output)
+        }
+        None => Err(output_not_found(network, network_config, l2_block, mode, pg_client).await),
+    }
+}
+
+/// A function that builds the error for a block without a matching output.
+/// A block without a covering output is ahead of the index, so the client is told how
+/// far the index goes.
+pub async fn output_not_found(
+    network: &Network,
+    network_config: &NetworkConfig,
+    l2_block: i64,
+    mode: LookupMode,
+    pg_client: &tokio_postgres::Client,
+) -> ApiError {
+    if mode != LookupMode::Covering {
+        return ApiError::OutputNotFound {
+            network: network.to_string(),
+            l2_block,
+        };
+    }
+    let highest_indexed_block = get_highest_l2_block(network, network_config, pg_client)
+        .await
+        .ok()
+        .map(|highest| highest.block_number as i64);
+    ApiError::BlockNotIndexed {
+        network: network.to_string(),
+        l2_block,
+        highest_indexed_block,
+    }
+}
+
+/// A function that gets the outputs matching many L2 blocks of a network at once,
+/// with a single query per table.
+/// Parameters:
+/// * network: The network to query
+/// * network_config: The config of the network
+/// * l2_blocks: The L2 block numbers, duplicates are allowed
+/// * mode: How outputs are matched to the blocks
+/// * pg_client: The postgres client
+///
+/// Returns:
+/// * The matching output of every block that has one, keyed by the requested block
+pub async fn get_output_roots(
+    network: &Network,
+    network_config: &NetworkConfig,
+    l2_blocks: &[i64],
+    mode: LookupMode,
+    pg_client: &tokio_postgres::Client,
+) -> Result<HashMap<i64, OutputType>, ApiError> {
+    let mut l2_blocks = l2_blocks.to_vec();
+    l2_blocks.sort_unstable();
+    l2_blocks.dedup();
+
+    let mut outputs = HashMap::with_capacity(l2_blocks.len());
+    let tables = output_tables(network, network_config);
+
+    // Newest table first, so a preceding lookup right after the transition can fall back
+    // to the legacy table
+    for (table, table_blocks) in tables.iter().rev() {
+        let blocks: Vec<i64> = l2_blocks
+            .iter()
+            .copied()
+            .filter(|l2_block| {
+                table_blocks.contains(l2_block)
+                    || (mode == LookupMode::Preceding
+                        && *l2_block > *table_blocks.end()
+                        && !outputs.contains_key(l2_block))
+            })
+            .collect();
+        if blocks.is_empty() {
+            continue;
+        }
+
+        debug!(
+            blocks = blocks.len(),
+            network = %network,
+            ?table,
+            ?mode,
+            "Looking up outputs"
+        );
+        let rows =
+            query_outputs_at(*table, network, network_config, &blocks, mode, pg_client).await?;
+        for (l2_block, output) in rows {
+            outputs.insert(l2_block, output);
+        }
+    }
+
+    Ok(outputs)
+}
+
+/// A function that gets the outputs matching L2 blocks from one table, running the
+/// lookup for every block of `$1` at once. The requested block is returned as `l2_block`.
+#[instrument(name = "db.query", skip_all, fields(db.system = "postgresql", network = %network, table = ?table, blocks = l2_blocks.len()))]
+async fn query_outputs_at(
+    table: OutputTable,
+    network: &Network,
+    network_config: &NetworkConfig,
+    l2_blocks: &[i64],
+    mode: LookupMode,
+    pg_client: &tokio_postgres::Client,
+) -> Result<Vec<(i64, OutputType)>, ApiError> {
+    let (condition, order) = mode.condition();
+    let select_query = format!(
+        "SELECT q.l2_block, o.*
+    FROM unnest($1::BIGINT[]) AS q(l2_block)
+    CROSS JOIN LATERAL (
+        SELECT {}
+        FROM {}
+        WHERE l2_block_number {} q.l2_block
+        AND {}
+        ORDER BY l2_block_number {}
+        LIMIT 1
+    ) o;",
+        table.columns(),
+        table.name(network),
+        condition,
+        table.filter(2),
+        order
+    );
+
+    let rows = match table {
+        OutputTable::FaultDisputeGames => {
+            let trusted_proposer = trusted_proposer(network, network_config)?;
+            pg_client
+                .query(&select_query, &[&l2_blocks, &trusted_proposer])
+                .await
+        }
+        OutputTable::OpStack | OutputTable::Arbitrum => {
+            pg_client.query(&select_query, &[&l2_blocks]).await
+        }
+    }
+    .map_err(not_indexed(network))?;
+    Ok(rows
+        .iter()
+        .map(|row| (row.get("l2_block"), table.output_from_row(row)))
+        .collect())
+}
+
+/// A function that gets the latest output proposed on L1 at or before an L1 timestamp.
+/// Parameters:
+/// * network: The network to query
+/// * network_config: The config of the network
+/// * l1_timestamp: The L1 timestamp
+/// * pg_client: The postgres client
+///
+/// Returns:
+/// * The output with the latest L1 timestamp, the highest L2 block among outputs proposed
+///   at the same time
+#[instrument(name = "db.query", skip_all, fields(db.system = "postgresql", network = %network, l1_timestamp))]
+pub async fn get_output_root_before_l1_timestamp(
+    network: &Network,
+    network_config: &NetworkConfig,
+    l1_timestamp: i64,
+    pg_client: &tokio_postgres::Client,
+) -> Result<OutputType, ApiError> {
+    if is_arbitrum_stack(network) {
+        return Err(ApiError::InvalidParams(format!(
+            "{} outputs have no L1 timestamp, look them up by L2 block",
+            network
+        )));
+    }
+
+    // Games may be created before the transition, so every table has to be searched
+    let mut latest: Option<((i64, i64), OutputType)> = None;
+    for (table, table_blocks) in output_tables(network, network_config) {
+        let select_query = format!(
+            "SELECT {timestamp}::BIGINT AS proposed_at, l2_block_number::BIGINT AS proposed_l2_block, {}
+    FROM {}
+    WHERE {timestamp} <= $1::BIGINT
+    AND l2_block_number BETWEEN $2::BIGINT AND $3::BIGINT
+    AND {}
+    ORDER BY {timestamp} DESC, l2_block_number DESC
+    LIMIT 1;",
+            table.columns(),
+            table.name(network),
+            table.filter(4),
+            timestamp = table.l1_timestamp_column()
+        );
+
+        let (table_from, table_to) = (*table_blocks.start(), *table_blocks.end());
+        let row = match table {
+            OutputTable::FaultDisputeGames => {
+                let trusted_proposer = trusted_proposer(network, network_config)?;
+                pg_client
+                    .query_opt(
+                        &select_query,
+                        &[&l1_timestamp, &table_from, &table_to, &trusted_proposer],
+                    )
+                    .await
+            }
+            OutputTable::OpStack | OutputTable::Arbitrum => {
+                pg_client
+                    .query_opt(&select_query, &[&l1_timestamp, &table_from, &table_to])
+                    .await
+            }
+        }
+        .map_err(not_indexed(network))?;
+
+        if let Some(row) = row {
+            let key: (i64, i64) = (row.get("proposed_at"), row.get("proposed_l2_block"));
+            if latest
+                .as_ref()
+                .is_none_or(|(latest_key, _)| key > *latest_key)
+            {
+                latest = Some((key, table.output_from_row(&row)));
+            }
+        }
+    }
+
+    latest
+        .map(|(_, output)| output)
+        .ok_or_else(|| ApiError::NoOutputBefore {
+            network: network.to_string(),
+            l1_timestamp,
+        })
+}
+
+/// A function that lists the outputs of a network in a range, ordered by L2 block, the
+/// first one indexed of every block.
+/// Parameters:
+/// * network: The network to query
+/// * network_config: The config of the network
+/// * range: The L2 block or L1 timestamp range
+/// * after: Only list outputs after this L2 block, the cursor of the previous page
+/// * limit: The maximum number of outputs to return
+/// * pg_client: The postgres client
+///
+/// Returns:
+/// * Up to `limit` outputs
+#[instrument(name = "db.query", skip_all, fields(db.system = "postgresql", network = %network, limit))]
+pub async fn list_output_roots(
+    network: &Network,
+    network_config: &NetworkConfig,
+    range: OutputRange,
+    after: Option<i64>,
+    limit: i64,
+    pg_client: &tokio_postgres::Client,
+) -> Result<Vec<OutputType>, ApiError> {
+    if is_arbitrum_stack(network) && matches!(range, OutputRange::Timestamps { .. }) {
+        return Err(ApiError::InvalidParams(format!(
+            "{} outputs have no L1 timestamp, list them by L2 block",
+            network
+        )));
+    }
+
+    let mut outputs = Vec::new();
+    // Tables are ordered by L2 block, so the next one is only needed to fill the page
+    for (table, table_blocks) in output_tables(network, network_config) {
+        let remaining = limit - outputs.len() as i64;
+        if remaining <= 0 {
+            break;
+        }
+
+        let (range_column, from, to) = match range {
+            OutputRange::L2Blocks { from, to } => ("l2_block_number", from, to),
+            OutputRange::Timestamps { from, to } => (table.l1_timestamp_column(), from, to),
+        };
+        // Several games, or legacy outputs proposed again, may be at the same block. One
+        // output is listed per block, as the cursor is the block of the last one.
+        let select_query = format!(
+            "SELECT DISTINCT ON (l2_block_number) {}
+    FROM {}
+    WHERE l2_block_number > $1::BIGINT
+    AND l2_block_number BETWEEN $2::BIGINT AND $3::BIGINT
+    AND {} BETWEEN $4::BIGINT AND $5::BIGINT
+    AND {}
+    ORDER BY l2_block_number ASC, {}
+    LIMIT $6;",
+            table.columns(),
+            table.name(network),
+            range_column,
+            table.filter(7),
+            match table {
+                OutputTable::FaultDisputeGames => "game_index ASC",
+                OutputTable::OpStack | OutputTable::Arbitrum => "id ASC",
+            }
+        );
+
+        let after = after.unwrap_or(-1);
+        let (table_from, table_to) = (*table_blocks.start(), *table_blocks.end());
+        let rows = match table {
+            OutputTable::FaultDisputeGames => {
+                let trusted_proposer = trusted_proposer(network, network_config)?;
+                pg_client
+                    .query(
+                        &select_query,
+                        &[
+                            &after,
+                            &table_from,
+                            &table_to,
+                            &from,
+                            &to,
+                            &remaining,
+                            &trusted_proposer,
+                        ],
+                    )
+                    .await
+            }
+            OutputTable::OpStack | OutputTable::Arbitrum => {
+                pg_client
+                    .query(
+                        &select_query,
+                        &[&after, &table_from, &table_to, &from, &to, &remaining],
+                    )
+                    .await
+            }
+        }
+        .map_err(not_indexed(network))?;
+        outputs.extend(rows.iter().map(|row| table.output_from_row(row)));
+    }
+
+    Ok(outputs)
+}
+
+/// An identifier an output proposal or dispute game can be looked up by
+#[derive(Clone, Debug)]
+pub enum OutputLookup {
+    L1TransactionHash(String),
+    L1BlockNumber(i64),
+    L2OutputIndex(i64),
+    GameIndex(i64),
+    GameAddress(String),
+}
+
+impl OutputLookup {
+    /// The condition matching the identifier, bound as `$1`, if the table stores it.
+    /// Hashes and addresses are stored as lowercase hex.
+    fn condition(&self, table: OutputTable) -> Option<&'static str> {
+        match (self, table) {
+            (OutputLookup::L1TransactionHash(_), _) => Some("l1_transaction_hash = lower($1)"),
+            (OutputLookup::L1BlockNumber(_), _) => Some("l1_block_number = $1::BIGINT"),
+            (OutputLookup::L2OutputIndex(_), OutputTable::OpStack) => {
+                Some("l2_output_index = $1::BIGINT")
+            }
+            (OutputLookup::GameIndex(_), OutputTable::FaultDisputeGames) => Some("game_index = $1"),
+            (OutputLookup::GameAddress(_), OutputTable::FaultDisputeGames) => {
+                Some("game_address = lower($1)")
+            }
+            _ => None,
+        }
+    }
+
+    fn value(&self) -> &(dyn tokio_postgres::types::ToSql + Sync) {
+        match self {
+            OutputLookup::L1TransactionHash(value) | OutputLookup::GameAddress(value) => value,
+            OutputLookup::L1BlockNumber(value)
+            | OutputLookup::L2OutputIndex(value)
+            | OutputLookup::GameIndex(value) => value,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            OutputLookup::L1TransactionHash(_) => "l1_transaction_hash",
+            OutputLookup::L1BlockNumber(_) => "l1_block_number",
+            OutputLookup::L2OutputIndex(_) => "l2_output_index",
+            OutputLookup::GameIndex(_) => "game_index",
+            OutputLookup::GameAddress(_) => "game_address",
+        }
+    }
+}
+
+/// A function that finds the output proposals and dispute games of a network by an
+/// identifier stored alongside them. Unlike the L2 block lookups every dispute game is
+/// matched, whatever its status or proposer.
+/// Parameters:
+/// * network: The network to query
+/// * network_config: The config of the network
+/// * lookup: The identifier to look up
+/// * limit: The maximum number of outputs to return
+/// * pg_client: The postgres client
+///
+/// Returns:
+/// * The matching outputs, ordered by L2 block
+#[instrument(name = "db.query", skip_all, fields(db.system = "postgresql", network = %network, limit))]
+pub async fn lookup_outputs(
+    network: &Network,
+    network_config: &NetworkConfig,
+    lookup: &OutputLookup,
+    limit: i64,
+    pg_client: &tokio_postgres::Client,
+) -> Result<Vec<OutputType>, ApiError> {
+    let tables: Vec<(OutputTable, &str)> = output_tables(network, network_config)
+        .into_iter()
+        .filter_map(|(table, _)| lookup.condition(table).map(|condition| (table, condition)))
+        .collect();
+    if tables.is_empty() {
+        return Err(ApiError::InvalidParams(format!(
+            "{} outputs cannot be looked up by {}",
+            network,
+            lookup.name()
+        )));
+    }
+
+    let mut outputs = Vec::new();
+    for (table, condition) in tables {
+        let select_query = format!(
+            "SELECT {}
+    FROM {}
+    WHERE {}
+    ORDER BY l2_block_number ASC
+    LIMIT $2;",
+            table.columns(),
+            table.name(network),
+            condition
+        );
+        let rows = pg_client
+            .query(&select_query, &[lookup.value(), &limit])
+            .await
+            .map_err(not_indexed(network))?;
+        outputs.extend(rows.iter().map(|row| table.output_from_row(row)));
+    }
+    outputs.truncate(limit as usize);
+
+    Ok(outputs)
+}
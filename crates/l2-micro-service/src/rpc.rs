@@ -0,0 +1,208 @@
+use std::time::Duration;
+
+use reqwest::{header, Client};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::{field, info_span, Instrument, Span};
+use utoipa::ToSchema;
+
+use crate::error::ApiError;
+use crate::telemetry;
+
+/// Timeout of a single L2 RPC request
+const RPC_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The L2ToL1MessagePasser predeploy, whose storage root is part of the output root preimage
+pub const MESSAGE_PASSER_ADDRESS: &str = "0x4200000000000000000000000000000000000016";
+
+/// The Merkle proof of an account and of some of its storage slots, as returned by
+/// `eth_getProof`
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+#[serde(rename_all(deserialize = "camelCase"))]
+pub struct AccountProof {
+    pub address: String,
+    pub balance: String,
+    pub nonce: String,
+    pub code_hash: String,
+    /// The storage root of the account
+    pub storage_hash: String,
+    /// The RLP encoded trie nodes from the state root to the account
+    pub account_proof: Vec<String>,
+    pub storage_proof: Vec<StorageProof>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct StorageProof {
+    pub key: String,
+    pub value: String,
+    /// The RLP encoded trie nodes from the storage root to the slot
+    pub proof: Vec<String>,
+}
+
+/// The fields of an L2 block header that are part of the output root preimage
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockHeader {
+    pub hash: String,
+    pub state_root: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct RpcResponse {
+    result: Option<Value>,
+    error: Option<RpcError>,
+}
+
+/// A JSON-RPC client for the L2 execution nodes, shared by all requests
+pub struct L2Rpc {
+    client: Client,
+}
+
+impl Default for L2Rpc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl L2Rpc {
+    pub fn new() -> Self {
+        Self {
+            client: Client::builder()
+                .timeout(RPC_TIMEOUT)
+                .build()
+                .expect("Failed to build the L2 RPC client"),
+        }
+    }
+
+    /// A function that returns the L2 RPC URL of a network, read from `<NETWORK>_RPC_URL`,
+    /// e.g. `OPTIMISM_MAINNET_RPC_URL`
+    pub fn url(network: &str) -> Result<String, ApiError> {
+        let var_name = format!("{}_RPC_URL", network.to_uppercase());
+        std::env::var(&var_name)
+            .map_err(|_| ApiError::Internal(format!("{} must be set to serve proofs", var_name)))
+    }
+
+    async fn call<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        method: &str,
+        params: Value,
+    ) -> Result<T, ApiError> {
+        let span = info_span!(
+            "rpc",
+            otel.kind = "client",
+            rpc.system = "jsonrpc",
+            rpc.method = method,
+            otel.status_code = field::Empty
+        );
+        let result = telemetry::observe_rpc(method, self.send(url, method, params))
+            .instrument(span.clone())
+            .await;
+        if result.is_err() {
+            span.record("otel.status_code", "ERROR");
+        }
+        result
+    }
+
+    async fn send<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        method: &str,
+        params: Value,
+    ) -> Result<T, ApiError> {
+        let rpc_request = json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params,
+            "id": 1,
+        });
+
+        let unavailable = |e: String| ApiError::L2RpcUnavailable(format!("{}: {}", method, e));
+        let mut request = self
+            .client
+            .post(url)
+            .header(header::CONTENT_TYPE, "application/json");
+        // Lets a traced RPC provider join the trace of the request
+        for (name, value) in common::trace_context_headers(&Span::current()) {
+            request = request.header(name, value);
+        }
+        let rpc_response = request
+            .json(&rpc_request)
+            .send()
+            .await
+            .map_err(|e| unavailable(e.without_url().to_string()))?;
+
+        if !rpc_response.status().is_success() {
+            return Err(unavailable(format!(
+                "request failed with HTTP {}",
+                rpc_response.status()
+            )));
+        }
+
+        let body: RpcResponse = rpc_response
+            .json()
+            .await
+            .map_err(|e| unavailable(e.without_url().to_string()))?;
+        if let Some(err) = body.error {
+            return Err(unavailable(format!(
+                "rpc error {}: {}",
+                err.code, err.message
+            )));
+        }
+        match body.result {
+            Some(Value::Null) | None => Err(unavailable("empty result".to_string())),
+            Some(result) => serde_json::from_value(result).map_err(|e| unavailable(e.to_string())),
+        }
+    }
+
+    /// A function that returns the header of an L2 block.
+    /// Parameters:
+    /// * url: The L2 RPC URL
+    /// * block_number: The L2 block number
+    ///
+    /// Returns:
+    /// * The block hash and state root of the block
+    pub async fn get_block_header(
+        &self,
+        url: &str,
+        block_number: i64,
+    ) -> Result<BlockHeader, ApiError> {
+        self.call(
+            url,
+            "eth_getBlockByNumber",
+            json!([format!("0x{:x}", block_number), false]),
+        )
+        .await
+    }
+
+    /// A function that returns the `eth_getProof` of an account at an L2 block.
+    /// Parameters:
+    /// * url: The L2 RPC URL
+    /// * address: The account address
+    /// * storage_keys: The storage slots to prove
+    /// * block_number: The L2 block number
+    ///
+    /// Returns:
+    /// * The account and storage proofs against the state root of the block
+    pub async fn get_proof(
+        &self,
+        url: &str,
+        address: &str,
+        storage_keys: &[String],
+        block_number: i64,
+    ) -> Result<AccountProof, ApiError> {
+        self.call(
+            url,
+            "eth_getProof",
+            json!([address, storage_keys, format!("0x{:x}", block_number)]),
+        )
+        .await
+    }
+}
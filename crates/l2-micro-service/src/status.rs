@@ -0,0 +1,108 @@
+use common::Network;
+use serde::Serialize;
+use tokio_postgres::error::SqlState;
+use tokio_postgres::Row;
+use tracing::instrument;
+use utoipa::ToSchema;
+
+use crate::error::ApiError;
+
+/// Progress and health of one stream of the indexer, as persisted in `indexer_status`
+#[derive(Serialize, ToSchema, Clone, Debug)]
+pub struct StreamStatus {
+    pub network: String,
+    /// `outputs` (legacy OutputProposed), `dispute_games` or `arbitrum`
+    pub stream: String,
+    /// Whether the stream stopped on a permanent error, see `last_error`
+    pub halted: bool,
+    /// The last L1 block indexed, unset until the stream indexed a batch
+    pub last_processed_l1_block: Option<i64>,
+    pub last_processed_l1_timestamp: Option<i64>,
+    /// The latest L1 block seen by the indexer
+    pub l1_head_block: Option<i64>,
+    pub l1_head_timestamp: Option<i64>,
+    /// How many L1 blocks the stream is behind the L1 head, block delay included
+    pub lag_blocks: Option<i64>,
+    /// How many seconds the stream is behind the L1 head
+    pub lag_seconds: Option<i64>,
+    /// Whether the stream indexed everything up to the last L1 block it may index, e.g.
+    /// the legacy outputs of a network that moved to dispute games
+    pub completed: bool,
+    /// The L1 timestamp of the last event indexed
+    pub last_event_l1_timestamp: Option<i64>,
+    /// The last error of the stream, cleared once it makes progress again
+    pub last_error: Option<String>,
+    /// When `last_error` happened, as a unix timestamp
+    pub last_error_at: Option<i64>,
+    /// When the indexer last wrote this status, as a unix timestamp. A stale value
+    /// means the indexer of the network is not running.
+    pub updated_at: i64,
+}
+
+impl StreamStatus {
+    fn from_row(row: &Row) -> Self {
+        let last_processed_l1_block: Option<i64> = row.get("last_processed_l1_block");
+        let last_processed_l1_timestamp: Option<i64> = row.get("last_processed_l1_timestamp");
+        let l1_head_block: Option<i64> = row.get("l1_head_block");
+        let l1_head_timestamp: Option<i64> = row.get("l1_head_timestamp");
+        let l1_block_cap: Option<i64> = row.get("l1_block_cap");
+
+        let completed = matches!(
+            (last_processed_l1_block, l1_block_cap),
+            (Some(processed), Some(cap)) if processed >= cap
+        );
+        let lag = |head: Option<i64>, processed: Option<i64>| {
+            if completed {
+                return Some(0);
+            }
+            Some((head? - processed?).max(0))
+        };
+
+        StreamStatus {
+            network: row.get("network"),
+            stream: row.get("stream"),
+            halted: row.get("halted"),
+            last_processed_l1_block,
+            last_processed_l1_timestamp,
+            l1_head_block,
+            l1_head_timestamp,
+            lag_blocks: lag(l1_head_block, last_processed_l1_block),
+            lag_seconds: lag(l1_head_timestamp, last_processed_l1_timestamp),
+            completed,
+            last_event_l1_timestamp: row.get("last_event_l1_timestamp"),
+            last_error: row.get("last_error"),
+            last_error_at: row.get("last_error_at"),
+            updated_at: row.get("updated_at"),
+        }
+    }
+}
+
+/// A function that returns the status of the indexer streams.
+/// Parameters:
+/// * network: Only the streams of this network, all of them if unset
+/// * pg_client: The postgres client
+///
+/// Returns:
+/// * The streams, ordered by network and stream. Empty when no indexer ever ran.
+#[instrument(name = "db.query", skip_all, fields(db.system = "postgresql", network = network.map(ToString::to_string)))]
+pub async fn get_indexer_status(
+    network: Option<&Network>,
+    pg_client: &tokio_postgres::Client,
+) -> Result<Vec<StreamStatus>, ApiError> {
+    let network = network.map(ToString::to_string);
+    let select_query =
+        "SELECT network, stream, halted, last_processed_l1_block, last_processed_l1_timestamp,
+        l1_head_block, l1_head_timestamp, l1_block_cap, last_event_l1_timestamp, last_error,
+        EXTRACT(EPOCH FROM last_error_at)::BIGINT AS last_error_at,
+        EXTRACT(EPOCH FROM updated_at)::BIGINT AS updated_at
+    FROM indexer_status
+    WHERE $1::VARCHAR IS NULL OR network = $1
+    ORDER BY network ASC, stream ASC;";
+
+    match pg_client.query(select_query, &[&network]).await {
+        Ok(rows) => Ok(rows.iter().map(StreamStatus::from_row).collect()),
+        // The indexer creates the table on its first start
+        Err(e) if e.code() == Some(&SqlState::UNDEFINED_TABLE) => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
@@ -0,0 +1,145 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use ::metrics::{counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram};
+use deadpool_postgres::Pool;
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::{Orbit, Request, Response, Rocket};
+
+/// Buckets of the latency histograms, in seconds
+const DURATION_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+/// How often histograms are compacted when nobody scrapes them
+const UPKEEP_PERIOD: Duration = Duration::from_secs(5);
+
+/// A function that installs the Prometheus recorder of the service.
+///
+/// Returns:
+/// * The handle rendering the metrics at `/metrics`
+pub fn install() -> eyre::Result<PrometheusHandle> {
+    let handle = PrometheusBuilder::new()
+        .set_buckets(DURATION_BUCKETS)?
+        .install_recorder()?;
+
+    describe_histogram!(
+        "http_request_duration_seconds",
+        "Latency of the HTTP requests by method, route and status"
+    );
+    describe_histogram!(
+        "l2_rpc_request_duration_seconds",
+        "Latency of the L2 RPC requests by method"
+    );
+    describe_counter!("l2_rpc_errors_total", "Failed L2 RPC requests by method");
+    describe_counter!(
+        "webhook_deliveries_total",
+        "Webhook events delivered or given up on, by result"
+    );
+    describe_counter!(
+        "api_requests_total",
+        "Requests let through, by API key name"
+    );
+    describe_counter!(
+        "api_requests_rejected_total",
+        "Requests refused by the API keys, by API key name and reason"
+    );
+    describe_counter!(
+        "output_cache_lookups_total",
+        "Output root lookups by cache result, hit or miss"
+    );
+    describe_counter!(
+        "output_cache_evictions_total",
+        "Output root lookups dropped from the full cache, the least recently used first"
+    );
+    describe_gauge!("live_event_streams", "Open /v1/events streams");
+    describe_gauge!("db_pool_max_size", "Maximum number of pooled connections");
+    describe_gauge!("db_pool_size", "Open pooled connections");
+    describe_gauge!("db_pool_available", "Idle pooled connections");
+    describe_gauge!(
+        "db_pool_waiting",
+        "Requests waiting for a pooled connection"
+    );
+
+    Ok(handle)
+}
+
+/// A function that samples the pool saturation, called on every scrape
+pub fn record_pool_status(pool: &Pool) {
+    let status = pool.status();
+    gauge!("db_pool_max_size").set(status.max_size as f64);
+    gauge!("db_pool_size").set(status.size as f64);
+    gauge!("db_pool_available").set(status.available as f64);
+    gauge!("db_pool_waiting").set(status.waiting as f64);
+}
+
+/// A function that times an L2 RPC request and counts its failures.
+/// Parameters:
+/// * method: The RPC method, e.g. eth_getProof
+/// * request: The request to run
+///
+/// Returns:
+/// * The result of the request
+pub async fn observe_rpc<T, E>(
+    method: &str,
+    request: impl Future<Output = Result<T, E>>,
+) -> Result<T, E> {
+    let started = Instant::now();
+    let result = request.await;
+    histogram!("l2_rpc_request_duration_seconds", "method" => method.to_string())
+        .record(started.elapsed().as_secs_f64());
+    if result.is_err() {
+        counter!("l2_rpc_errors_total", "method" => method.to_string()).increment(1);
+    }
+    result
+}
+
+/// The time a request has been handled for, measured by [`MetricsFairing`]
+pub fn request_duration(request: &Request<'_>) -> Duration {
+    request.local_cache(Instant::now).elapsed()
+}
+
+/// Times every request into `http_request_duration_seconds`, labelled by the route
+/// template rather than the path so that the number of series stays bounded
+pub struct MetricsFairing {
+    pub handle: PrometheusHandle,
+}
+
+#[rocket::async_trait]
+impl Fairing for MetricsFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "Prometheus metrics",
+            kind: Kind::Liftoff | Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_liftoff(&self, _: &Rocket<Orbit>) {
+        let handle = self.handle.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(UPKEEP_PERIOD).await;
+                handle.run_upkeep();
+            }
+        });
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut rocket::Data<'_>) {
+        let start_time = Instant::now();
+        request.local_cache(|| start_time);
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let route = request
+            .route()
+            .map(|route| route.uri.path().to_string())
+            .unwrap_or_else(|| "unmatched".to_string());
+        histogram!(
+            "http_request_duration_seconds",
+            "method" => request.method().as_str(),
+            "route" => route,
+            "status" => response.status().code.to_string()
+        )
+        .record(request_duration(request).as_secs_f64());
+    }
+}
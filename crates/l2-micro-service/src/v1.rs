@@ -0,0 +1,1333 @@
+//! Versioned API mounted under `/v1`.
+//!
+//! Every output is returned with the same schema whatever the network, tagged with a
+//! `type` discriminator, and the OpenAPI document generated from these types is served
+//! at `/v1/openapi.json`.
+
+use std::collections::{BTreeMap, HashMap};
+
+use deadpool_postgres::Pool;
+use rocket::form::{self, FromForm};
+use rocket::http::Status;
+use rocket::response::status::Created;
+use rocket::response::stream::{Event, EventStream};
+use rocket::serde::json::{self, Json};
+use rocket::tokio::sync::broadcast::error::RecvError;
+use rocket::{Route, Shutdown, State};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+use common::{LiveEvent, LiveEventType, OutputRootPreimage};
+
+use crate::cache::{self, CachedJson, Freshness, OutputCache};
+use crate::db;
+use crate::error::{ApiError, ErrorBody};
+use crate::games::{self, DisputeGameDetail, GameFilter, GameStatus};
+use crate::live::{LiveFeed, StreamGuard};
+use crate::queries::{
+    self, parse_network, ArbitrumParamsOutput, LookupMode, OPStackFaultDisputeGameOutput,
+    OPStackParamsOutput, OutputLookup, OutputRange, OutputType,
+};
+use crate::rpc::{AccountProof, L2Rpc, StorageProof, MESSAGE_PASSER_ADDRESS};
+use crate::status::{self, StreamStatus};
+use crate::webhooks::{self, Webhook, WebhookEvent, WebhookRequest};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "L2 Indexer API",
+        description = "Output roots of L2s proven on L1"
+    ),
+    paths(
+        get_output_root,
+        post_output_roots,
+        list_output_roots,
+        lookup_outputs,
+        get_storage_proof,
+        list_dispute_games,
+        get_dispute_game,
+        get_highest_l2_block,
+        get_indexer_status,
+        post_webhook,
+        get_webhook,
+        delete_webhook,
+        stream_events,
+        get_openapi
+    ),
+    components(schemas(
+        OutputRoot,
+        OpStackOutput,
+        OpStackFdgOutput,
+        ArbitrumOutput,
+        DisputeGame,
+        OutputPreimage,
+        HighestL2Block,
+        OutputRootsRequest,
+        OutputRootItem,
+        OutputRootsResponse,
+        OutputRootResult,
+        OutputRootPage,
+        OutputRootList,
+        StorageProofBundle,
+        AccountProof,
+        StorageProof,
+        DisputeGamePage,
+        IndexerStatusList,
+        StreamStatus,
+        DisputeGameDetail,
+        GameStatus,
+        LookupMode,
+        WebhookRequest,
+        Webhook,
+        WebhookEvent,
+        LiveEvent,
+        LiveEventType,
+        ErrorBody
+    ))
+)]
+pub struct ApiDoc;
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        get_output_root,
+        post_output_roots,
+        list_output_roots,
+        lookup_outputs,
+        get_storage_proof,
+        list_dispute_games,
+        get_dispute_game,
+        get_highest_l2_block,
+        get_indexer_status,
+        post_webhook,
+        get_webhook,
+        delete_webhook,
+        stream_events
+    ]
+}
+
+/// The routes served without an API key
+pub fn public_routes() -> Vec<Route> {
+    routes![get_openapi]
+}
+
+/// An output root proven on L1, tagged by the kind of proof system that produced it
+#[derive(Serialize, ToSchema, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum OutputRoot {
+    /// Output proposed to the legacy OP stack L2OutputOracle
+    OpStack(OpStackOutput),
+    /// Output claimed by an OP stack fault dispute game
+    OpStackFdg(OpStackFdgOutput),
+    /// Send root posted to the Arbitrum Outbox
+    Arbitrum(ArbitrumOutput),
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct OpStackOutput {
+    pub network: String,
+    pub l2_output_root: String,
+    pub l2_block_number: i64,
+    pub l2_output_index: i64,
+    pub l1_timestamp: i64,
+    pub l1_transaction_hash: String,
+    pub l1_block_number: i64,
+    pub l1_transaction_index: i64,
+    pub l1_block_hash: String,
+    /// Unset for outputs whose preimage the indexer could not fetch from the L2 RPC yet
+    pub preimage: Option<OutputPreimage>,
+}
+
+/// The preimage of an OP stack output root:
+/// `l2_output_root = keccak256(version ++ state_root ++ message_passer_storage_root ++ block_hash)`
+#[derive(Serialize, ToSchema, Debug)]
+pub struct OutputPreimage {
+    pub version: String,
+    pub state_root: String,
+    /// The storage root of the L2ToL1MessagePasser
+    pub message_passer_storage_root: String,
+    pub block_hash: String,
+    /// Whether the keccak256 of the preimage is `l2_output_root`
+    pub verified: bool,
+}
+
+impl OutputPreimage {
+    fn new(
+        l2_output_root: &str,
+        state_root: Option<String>,
+        message_passer_storage_root: Option<String>,
+        block_hash: Option<String>,
+    ) -> Option<Self> {
+        let preimage =
+            OutputRootPreimage::v0(state_root?, message_passer_storage_root?, block_hash?);
+        Some(Self {
+            verified: preimage.verify(l2_output_root),
+            version: preimage.version,
+            state_root: preimage.state_root,
+            message_passer_storage_root: preimage.message_passer_storage_root,
+            block_hash: preimage.block_hash,
+        })
+    }
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct OpStackFdgOutput {
+    pub network: String,
+    /// The root claim of the dispute game
+    pub l2_output_root: String,
+    pub l2_block_number: i64,
+    /// Unset when the L2 block could not be fetched, only returned by lookups
+    pub l2_block_hash: Option<String>,
+    pub l2_state_root: Option<String>,
+    pub l2_withdrawal_storage_root: Option<String>,
+    /// The output root version, the first word of the output root preimage
+    pub version_byte: String,
+    pub dispute_game: DisputeGame,
+    pub l1_transaction_hash: String,
+    pub l1_block_number: i64,
+    pub l1_transaction_index: i64,
+    pub l1_block_hash: String,
+    /// The preimage of the root claim, unset when the L2 block could not be fetched
+    pub preimage: Option<OutputPreimage>,
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct DisputeGame {
+    pub game_index: i64,
+    pub game_address: String,
+    pub game_type: i64,
+    /// 0: IN_PROGRESS, 1: CHALLENGER_WINS, 2: DEFENDER_WINS
+    pub game_state: i64,
+    pub proposer_address: String,
+    /// The L1 timestamp the game was created at
+    pub created_at: i64,
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct ArbitrumOutput {
+    pub network: String,
+    /// The send root posted to the Outbox
+    pub l2_output_root: String,
+    pub l2_block_number: i64,
+    pub l2_block_hash: String,
+    pub l1_transaction_hash: String,
+    pub l1_block_number: i64,
+    pub l1_transaction_index: i64,
+    pub l1_block_hash: String,
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct HighestL2Block {
+    pub network: String,
+    pub l2_block_number: i64,
+}
+
+impl OutputRoot {
+    pub fn new(network: &str, output: OutputType) -> Self {
+        let network = network.to_string();
+        match output {
+            OutputType::OpStack(output) => OutputRoot::OpStack(OpStackOutput::new(network, output)),
+            OutputType::OpStackFDG(output) => {
+                OutputRoot::OpStackFdg(OpStackFdgOutput::new(network, output))
+            }
+            OutputType::Arbitrum(output) => {
+                OutputRoot::Arbitrum(ArbitrumOutput::new(network, output))
+            }
+        }
+    }
+}
+
+impl OutputRoot {
+    /// The L2 block the output is for
+    pub fn l2_block_number(&self) -> i64 {
+        match self {
+            OutputRoot::OpStack(output) => output.l2_block_number,
+            OutputRoot::OpStackFdg(output) => output.l2_block_number,
+            OutputRoot::Arbitrum(output) => output.l2_block_number,
+        }
+    }
+}
+
+impl OpStackOutput {
+    fn new(network: String, output: OPStackParamsOutput) -> Self {
+        Self {
+            preimage: OutputPreimage::new(
+                &output.l2_output_root,
+                output.l2_state_root,
+                output.l2_withdrawal_storage_root,
+                output.l2_block_hash,
+            ),
+            network,
+            l2_output_root: output.l2_output_root,
+            l2_block_number: output.l2_block_number.into(),
+            l2_output_index: output.l2_output_index.into(),
+            l1_timestamp: output.l1_timestamp.into(),
+            l1_transaction_hash: output.l1_transaction_hash,
+            l1_block_number: output.l1_block_number.into(),
+            l1_transaction_index: output.l1_transaction_index.into(),
+            l1_block_hash: output.l1_block_hash,
+        }
+    }
+}
+
+impl OpStackFdgOutput {
+    fn new(network: String, output: OPStackFaultDisputeGameOutput) -> Self {
+        Self {
+            preimage: OutputPreimage::new(
+                &output.root_claim,
+                output.l2_state_root.clone(),
+                output.l2_withdrawal_storage_root.clone(),
+                output.l2_block_hash.clone(),
+            ),
+            network,
+            l2_output_root: output.root_claim,
+            l2_block_number: output.l2_block_number,
+            l2_block_hash: output.l2_block_hash,
+            l2_state_root: output.l2_state_root,
+            l2_withdrawal_storage_root: output.l2_withdrawal_storage_root,
+            version_byte: output.version_byte,
+            dispute_game: DisputeGame {
+                game_index: output.game_index,
+                game_address: output.game_address,
+                game_type: output.game_type,
+                game_state: output.game_state,
+                proposer_address: output.proposer_address,
+                created_at: output.timestamp,
+            },
+            l1_transaction_hash: output.l1_transaction_hash,
+            l1_block_number: output.l1_block_number,
+            l1_transaction_index: output.l1_transaction_index,
+            l1_block_hash: output.l1_block_hash,
+        }
+    }
+}
+
+impl ArbitrumOutput {
+    fn new(network: String, output: ArbitrumParamsOutput) -> Self {
+        Self {
+            network,
+            l2_output_root: output.l2_output_root,
+            l2_block_number: output.l2_block_number.into(),
+            l2_block_hash: output.l2_block_hash,
+            l1_transaction_hash: output.l1_transaction_hash,
+            l1_block_number: output.l1_block_number.into(),
+            l1_transaction_index: output.l1_transaction_index.into(),
+            l1_block_hash: output.l1_block_hash,
+        }
+    }
+}
+
+/// A function that unwraps an optional query parameter.
+/// Rocket reads an unparsable optional field as a missing one, so optional fields
+/// that must be validated are parsed as results and their errors reported here.
+fn optional_param<T>(
+    name: &str,
+    value: Option<form::Result<'_, T>>,
+) -> Result<Option<T>, ApiError> {
+    match value {
+        Some(Ok(value)) => Ok(Some(value)),
+        Some(Err(errors))
+            if errors
+                .iter()
+                .all(|e| matches!(e.kind, form::error::ErrorKind::Missing)) =>
+        {
+            Ok(None)
+        }
+        Some(Err(errors)) => Err(ApiError::InvalidParams(format!(
+            "{}: {}",
+            name,
+            errors
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))),
+        None => Ok(None),
+    }
+}
+
+#[derive(FromForm, IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+pub struct OutputRootQuery<'r> {
+    /// The network name, e.g. `optimism_mainnet`
+    network: String,
+    /// The L2 block to get the output root of
+    #[param(value_type = Option<i64>)]
+    l2_block: Option<form::Result<'r, i64>>,
+    /// An L2 timestamp, resolved to the last L2 block produced at or before it
+    #[param(value_type = Option<i64>)]
+    l2_timestamp: Option<form::Result<'r, i64>>,
+    /// An L1 timestamp, to get the latest output proposed at or before it
+    #[param(value_type = Option<i64>)]
+    l1_timestamp: Option<form::Result<'r, i64>>,
+    /// How the output is matched to `l2_block` or `l2_timestamp`, `covering` by default
+    #[param(inline, value_type = Option<LookupMode>)]
+    mode: Option<form::Result<'r, LookupMode>>,
+}
+
+/// A function that resolves an L2 timestamp to the last L2 block produced at or before it,
+/// from the genesis and block time of the network
+fn l2_block_at_timestamp(network: &str, l2_timestamp: i64) -> Result<i64, ApiError> {
+    let (network, network_config) = parse_network(network)?;
+    if network_config.l2_genesis_timestamp.is_none() || network_config.l2_block_time_sec.is_none() {
+        return Err(ApiError::InvalidParams(format!(
+            "{} has no L2 block time configured, look outputs up by l2_block",
+            network
+        )));
+    }
+    u64::try_from(l2_timestamp)
+        .ok()
+        .and_then(|l2_timestamp| network_config.l2_block_at_timestamp(l2_timestamp))
+        .map(|l2_block| l2_block as i64)
+        .ok_or_else(|| {
+            ApiError::InvalidParams(format!(
+                "l2_timestamp {} is before the L2 genesis of {}",
+                l2_timestamp, network
+            ))
+        })
+}
+
+#[derive(FromForm, IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+pub struct NetworkQuery {
+    /// The network name, e.g. `optimism_mainnet`
+    network: String,
+}
+
+/// Returns the output root matching `l2_block`: by default the first one proven at or
+/// after it, with `mode=exact` only one at exactly it, with `mode=preceding` the last
+/// one at or before it.
+///
+/// Instead of `l2_block`, `l2_timestamp` looks the output up for the last L2 block produced
+/// at or before it, and `l1_timestamp` returns the latest output proposed on L1 at or
+/// before it.
+#[utoipa::path(
+    get,
+    path = "/v1/output-root",
+    params(OutputRootQuery),
+    responses(
+        (status = 200, description = "The matching output root", body = OutputRoot),
+        (status = 400, description = "Unknown network", body = ErrorBody),
+        (status = 404, description = "The block is not indexed yet, or no output matches it", body = ErrorBody),
+        (status = 422, description = "Invalid query parameters, or a network without the timestamps needed", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    )
+)]
+#[get("/output-root?<query..>")]
+async fn get_output_root(
+    query: form::Result<'_, OutputRootQuery<'_>>,
+    pool: &State<Pool>,
+    output_cache: &State<OutputCache>,
+) -> Result<CachedJson<OutputRoot>, ApiError> {
+    let query = query.map_err(|e| ApiError::InvalidParams(e.to_string()))?;
+    let mode = optional_param("mode", query.mode)?;
+    let l2_block = match (
+        optional_param("l2_block", query.l2_block)?,
+        optional_param("l2_timestamp", query.l2_timestamp)?,
+        optional_param("l1_timestamp", query.l1_timestamp)?,
+    ) {
+        (Some(l2_block), None, None) => l2_block,
+        (None, Some(l2_timestamp), None) => l2_block_at_timestamp(&query.network, l2_timestamp)?,
+        (None, None, Some(l1_timestamp)) => {
+            if mode.is_some() {
+                return Err(ApiError::InvalidParams(
+                    "mode cannot be used with l1_timestamp".to_string(),
+                ));
+            }
+            let (network, network_config) = parse_network(&query.network)?;
+            let pg_client = db::get_client(pool).await?;
+            let output = queries::get_output_root_before_l1_timestamp(
+                &network,
+                &network_config,
+                l1_timestamp,
+                &pg_client,
+            )
+            .await?;
+            // A later output may still be proposed before l1_timestamp if it is recent
+            return Ok(CachedJson {
+                body: OutputRoot::new(&query.network, output),
+                freshness: Freshness::Revalidate,
+            });
+        }
+        _ => {
+            return Err(ApiError::InvalidParams(
+                "exactly one of l2_block, l2_timestamp or l1_timestamp must be set".to_string(),
+            ))
+        }
+    };
+
+    let mode = mode.unwrap_or_default();
+    let output = crate::output_root(&query.network, l2_block, mode, pool, output_cache).await?;
+    Ok(CachedJson {
+        freshness: cache::freshness(&output, l2_block, mode),
+        body: OutputRoot::new(&query.network, output),
+    })
+}
+
+/// The maximum number of items of a batch request
+const MAX_BATCH_SIZE: usize = 1000;
+
+#[derive(Deserialize, ToSchema, Debug)]
+pub struct OutputRootsRequest {
+    /// The blocks to resolve, possibly across networks
+    pub queries: Vec<OutputRootItem>,
+}
+
+#[derive(Deserialize, ToSchema, Debug)]
+pub struct OutputRootItem {
+    /// The network name, e.g. `optimism_mainnet`
+    pub network: String,
+    /// The L2 block to get the output root of
+    pub l2_block: i64,
+    /// How the output is matched to `l2_block`, `covering` by default
+    #[serde(default)]
+    pub mode: LookupMode,
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct OutputRootsResponse {
+    /// One result per query, in the order of the request
+    pub results: Vec<OutputRootResult>,
+}
+
+/// The result of one query of a batch, either `output` or `error` is set
+#[derive(Serialize, ToSchema, Debug)]
+pub struct OutputRootResult {
+    pub network: String,
+    pub l2_block: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<OutputRoot>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorBody>,
+}
+
+impl OutputRootResult {
+    fn new(item: &OutputRootItem, result: Result<OutputRoot, ErrorBody>) -> Self {
+        let (output, error) = match result {
+            Ok(output) => (Some(output), None),
+            Err(error) => (None, Some(error)),
+        };
+        Self {
+            network: item.network.clone(),
+            l2_block: item.l2_block,
+            output,
+            error,
+        }
+    }
+}
+
+/// Returns the output roots matching many L2 blocks, possibly across networks.
+///
+/// Invalid items get a per-item error, while a database failure fails the whole batch.
+#[utoipa::path(
+    post,
+    path = "/v1/output-roots",
+    request_body = OutputRootsRequest,
+    responses(
+        (status = 200, description = "One result per query, in the order of the request", body = OutputRootsResponse),
+        (status = 413, description = "Request body over the json limit", body = ErrorBody),
+        (status = 422, description = "Invalid request body", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    )
+)]
+#[post("/output-roots", format = "json", data = "<request>")]
+async fn post_output_roots(
+    request: Result<Json<OutputRootsRequest>, json::Error<'_>>,
+    pool: &State<Pool>,
+) -> Result<Json<OutputRootsResponse>, ApiError> {
+    let request = request.map_err(ApiError::from)?;
+    if request.queries.len() > MAX_BATCH_SIZE {
+        return Err(ApiError::InvalidParams(format!(
+            "at most {} queries are allowed per batch, got {}",
+            MAX_BATCH_SIZE,
+            request.queries.len()
+        )));
+    }
+
+    // Group the valid blocks by network and mode so every table is queried once per mode
+    let mut blocks_by_network: BTreeMap<(&str, LookupMode), Vec<i64>> = BTreeMap::new();
+    for item in request.queries.iter().filter(|item| item.l2_block >= 0) {
+        blocks_by_network
+            .entry((item.network.as_str(), item.mode))
+            .or_default()
+            .push(item.l2_block);
+    }
+
+    let pg_client = db::get_client(pool).await?;
+    // Per network and mode, the found outputs and the highest indexed block if some
+    // covering lookups are ahead of the index
+    let mut resolved = HashMap::new();
+    for ((network_name, mode), l2_blocks) in blocks_by_network {
+        let (network, network_config) = match parse_network(network_name) {
+            Ok(network) => network,
+            Err(e) => {
+                resolved.insert((network_name, mode), Err(e.body()));
+                continue;
+            }
+        };
+        let outputs =
+            queries::get_output_roots(&network, &network_config, &l2_blocks, mode, &pg_client)
+                .await?;
+        let highest_indexed_block =
+            if mode != LookupMode::Covering || l2_blocks.iter().all(|b| outputs.contains_key(b)) {
+                None
+            } else {
+                queries::get_highest_l2_block(&network, &network_config, &pg_client)
+                    .await
+                    .ok()
+                    .map(|highest| highest.block_number as i64)
+            };
+        resolved.insert((network_name, mode), Ok((outputs, highest_indexed_block)));
+    }
+
+    let results = request
+        .queries
+        .iter()
+        .map(|item| {
+            let result = if item.l2_block < 0 {
+                Err(ApiError::InvalidParams(format!(
+                    "l2_block must not be negative, got {}",
+                    item.l2_block
+                ))
+                .body())
+            } else {
+                match &resolved[&(item.network.as_str(), item.mode)] {
+                    Ok((outputs, highest_indexed_block)) => match outputs.get(&item.l2_block) {
+                        Some(output) => Ok(OutputRoot::new(&item.network, output.clone())),
+                        None if item.mode == LookupMode::Covering => {
+                            Err(ApiError::BlockNotIndexed {
+                                network: item.network.clone(),
+                                l2_block: item.l2_block,
+                                highest_indexed_block: *highest_indexed_block,
+                            }
+                            .body())
+                        }
+                        None => Err(ApiError::OutputNotFound {
+                            network: item.network.clone(),
+                            l2_block: item.l2_block,
+                        }
+                        .body()),
+                    },
+                    Err(error) => Err(error.clone()),
+                }
+            };
+            OutputRootResult::new(item, result)
+        })
+        .collect();
+
+    Ok(Json(OutputRootsResponse { results }))
+}
+
+/// The default and maximum page size of listings
+const DEFAULT_PAGE_SIZE: i64 = 100;
+const MAX_PAGE_SIZE: i64 = 1000;
+
+#[derive(FromForm, IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+pub struct OutputRangeQuery<'r> {
+    /// The network name, e.g. `optimism_mainnet`
+    network: String,
+    /// The first L2 block of the range, used with `to_l2_block`
+    #[param(value_type = Option<i64>)]
+    from_l2_block: Option<form::Result<'r, i64>>,
+    /// The last L2 block of the range, used with `from_l2_block`
+    #[param(value_type = Option<i64>)]
+    to_l2_block: Option<form::Result<'r, i64>>,
+    /// The first L1 timestamp of the range, used with `to_timestamp`
+    #[param(value_type = Option<i64>)]
+    from_timestamp: Option<form::Result<'r, i64>>,
+    /// The last L1 timestamp of the range, used with `from_timestamp`
+    #[param(value_type = Option<i64>)]
+    to_timestamp: Option<form::Result<'r, i64>>,
+    /// The number of outputs per page, 100 by default and at most 1000
+    #[param(value_type = Option<i64>)]
+    limit: Option<form::Result<'r, i64>>,
+    /// The `next_cursor` of the previous page
+    #[param(value_type = Option<i64>)]
+    cursor: Option<form::Result<'r, i64>>,
+}
+
+impl OutputRangeQuery<'_> {
+    fn range(&mut self) -> Result<OutputRange, ApiError> {
+        let range = match (
+            optional_param("from_l2_block", self.from_l2_block.take())?,
+            optional_param("to_l2_block", self.to_l2_block.take())?,
+            optional_param("from_timestamp", self.from_timestamp.take())?,
+            optional_param("to_timestamp", self.to_timestamp.take())?,
+        ) {
+            (Some(from), Some(to), None, None) => OutputRange::L2Blocks { from, to },
+            (None, None, Some(from), Some(to)) => OutputRange::Timestamps { from, to },
+            _ => {
+                return Err(ApiError::InvalidParams(
+                    "either from_l2_block and to_l2_block, or from_timestamp and to_timestamp must be set"
+                        .to_string(),
+                ))
+            }
+        };
+        let (OutputRange::L2Blocks { from, to } | OutputRange::Timestamps { from, to }) = range;
+        if from > to {
+            return Err(ApiError::InvalidParams(format!(
+                "the range start {} is after its end {}",
+                from, to
+            )));
+        }
+        Ok(range)
+    }
+}
+
+/// A page of outputs, ordered by L2 block
+#[derive(Serialize, ToSchema, Debug)]
+pub struct OutputRootPage {
+    pub items: Vec<OutputRoot>,
+    /// Pass as `cursor` to get the next page, unset on the last page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<i64>,
+}
+
+/// Lists the output roots of a network between two L2 blocks or two L1 timestamps
+#[utoipa::path(
+    get,
+    path = "/v1/output-roots",
+    params(OutputRangeQuery),
+    responses(
+        (status = 200, description = "A page of output roots", body = OutputRootPage),
+        (status = 400, description = "Unknown network", body = ErrorBody),
+        (status = 422, description = "Invalid query parameters", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    )
+)]
+#[get("/output-roots?<query..>")]
+async fn list_output_roots(
+    query: form::Result<'_, OutputRangeQuery<'_>>,
+    pool: &State<Pool>,
+) -> Result<Json<OutputRootPage>, ApiError> {
+    let mut query = query.map_err(|e| ApiError::InvalidParams(e.to_string()))?;
+    let range = query.range()?;
+    let limit = optional_param("limit", query.limit)?.unwrap_or(DEFAULT_PAGE_SIZE);
+    let cursor = optional_param("cursor", query.cursor)?;
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        return Err(ApiError::InvalidParams(format!(
+            "limit must be between 1 and {}, got {}",
+            MAX_PAGE_SIZE, limit
+        )));
+    }
+
+    let (network, network_config) = parse_network(&query.network)?;
+    let pg_client = db::get_client(pool).await?;
+    // Fetch one more output than asked to know whether there is a next page
+    let mut outputs = queries::list_output_roots(
+        &network,
+        &network_config,
+        range,
+        cursor,
+        limit + 1,
+        &pg_client,
+    )
+    .await?;
+
+    let has_more = outputs.len() as i64 > limit;
+    outputs.truncate(limit as usize);
+    let items: Vec<OutputRoot> = outputs
+        .into_iter()
+        .map(|output| OutputRoot::new(&query.network, output))
+        .collect();
+    let next_cursor = if has_more {
+        items.last().map(OutputRoot::l2_block_number)
+    } else {
+        None
+    };
+
+    Ok(Json(OutputRootPage { items, next_cursor }))
+}
+
+#[derive(FromForm, IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+pub struct LookupQuery<'r> {
+    /// The network name, e.g. `optimism_mainnet`
+    network: String,
+    /// The L1 transaction that proposed the output or created the game
+    l1_transaction_hash: Option<String>,
+    /// The L1 block the output was proposed or the game was created in
+    #[param(value_type = Option<i64>)]
+    l1_block_number: Option<form::Result<'r, i64>>,
+    /// The index of the output in the legacy L2OutputOracle
+    #[param(value_type = Option<i64>)]
+    l2_output_index: Option<form::Result<'r, i64>>,
+    /// The index of the game in the DisputeGameFactory
+    #[param(value_type = Option<i64>)]
+    game_index: Option<form::Result<'r, i64>>,
+    /// The address of the dispute game
+    game_address: Option<String>,
+}
+
+impl LookupQuery<'_> {
+    fn lookup(self) -> Result<OutputLookup, ApiError> {
+        let lookups: Vec<OutputLookup> = [
+            self.l1_transaction_hash
+                .map(OutputLookup::L1TransactionHash),
+            optional_param("l1_block_number", self.l1_block_number)?
+                .map(OutputLookup::L1BlockNumber),
+            optional_param("l2_output_index", self.l2_output_index)?
+                .map(OutputLookup::L2OutputIndex),
+            optional_param("game_index", self.game_index)?.map(OutputLookup::GameIndex),
+            self.game_address.map(OutputLookup::GameAddress),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        match <[OutputLookup; 1]>::try_from(lookups) {
+            Ok([lookup]) => Ok(lookup),
+            Err(_) => Err(ApiError::InvalidParams(
+                "exactly one of l1_transaction_hash, l1_block_number, l2_output_index, game_index or game_address must be set"
+                    .to_string(),
+            )),
+        }
+    }
+}
+
+/// The outputs matching a lookup
+#[derive(Serialize, ToSchema, Debug)]
+pub struct OutputRootList {
+    pub items: Vec<OutputRoot>,
+}
+
+/// Finds the outputs of a network by L1 transaction hash, L1 block, output index,
+/// game index or game address.
+///
+/// Every dispute game is matched, whatever its status or proposer, so check
+/// `dispute_game.game_state` before trusting a claim.
+#[utoipa::path(
+    get,
+    path = "/v1/lookup",
+    params(LookupQuery),
+    responses(
+        (status = 200, description = "The matching outputs, ordered by L2 block", body = OutputRootList),
+        (status = 400, description = "Unknown network", body = ErrorBody),
+        (status = 422, description = "Invalid query parameters, or an identifier the network does not have", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    )
+)]
+#[get("/lookup?<query..>")]
+async fn lookup_outputs(
+    query: form::Result<'_, LookupQuery<'_>>,
+    pool: &State<Pool>,
+) -> Result<Json<OutputRootList>, ApiError> {
+    let query = query.map_err(|e| ApiError::InvalidParams(e.to_string()))?;
+    let network_name = query.network.clone();
+    let (network, network_config) = parse_network(&network_name)?;
+    let lookup = query.lookup()?;
+
+    let pg_client = db::get_client(pool).await?;
+    let outputs = queries::lookup_outputs(
+        &network,
+        &network_config,
+        &lookup,
+        MAX_PAGE_SIZE,
+        &pg_client,
+    )
+    .await?;
+
+    Ok(Json(OutputRootList {
+        items: outputs
+            .into_iter()
+            .map(|output| OutputRoot::new(&network_name, output))
+            .collect(),
+    }))
+}
+
+/// The maximum number of storage slots of a proof request
+const MAX_PROOF_SLOTS: usize = 100;
+
+#[derive(FromForm, IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+pub struct StorageProofQuery {
+    /// The network name, e.g. `optimism_mainnet`
+    network: String,
+    /// The L2 block the state is proven at, resolved to the output covering it
+    l2_block: i64,
+    /// The L2 account to prove
+    account: String,
+    /// A storage slot of the account to prove, can be repeated
+    #[field(name = "slot")]
+    #[param(rename = "slot", required = false)]
+    slots: Vec<String>,
+}
+
+/// A function that checks that a value is 0x prefixed hex of at most `max_bytes` bytes
+fn is_hex(value: &str, max_bytes: usize) -> bool {
+    value.strip_prefix("0x").is_some_and(|digits| {
+        !digits.is_empty()
+            && digits.len() <= max_bytes * 2
+            && digits.chars().all(|c| c.is_ascii_hexdigit())
+    })
+}
+
+/// Everything needed to prove L2 state against an output root proven on L1
+#[derive(Serialize, ToSchema, Debug)]
+pub struct StorageProofBundle {
+    /// The output covering the requested block, the proofs are at its `l2_block_number`
+    pub output: OutputRoot,
+    /// The output root preimage read from the L2 RPC, `verified` tells whether it hashes
+    /// to the output root
+    pub preimage: OutputPreimage,
+    /// The proof of the account and of its slots against `preimage.state_root`
+    pub proof: AccountProof,
+}
+
+/// Returns the output covering `l2_block`, as `/v1/output-root` does, together with
+/// its preimage and the `eth_getProof` of `account` and its `slot`s at the L2 block of
+/// the output.
+///
+/// The proofs are read from the L2 RPC of the network, set with `<NETWORK>_RPC_URL`.
+/// Check `preimage.verified` before using them.
+#[utoipa::path(
+    get,
+    path = "/v1/storage-proof",
+    params(StorageProofQuery),
+    responses(
+        (status = 200, description = "The output root, its preimage and the proofs", body = StorageProofBundle),
+        (status = 400, description = "Unknown network", body = ErrorBody),
+        (status = 404, description = "The block is not indexed yet", body = ErrorBody),
+        (status = 422, description = "Invalid query parameters, or a network without state root outputs", body = ErrorBody),
+        (status = 502, description = "The L2 RPC failed, e.g. it does not keep the state of the block", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    )
+)]
+#[get("/storage-proof?<query..>")]
+async fn get_storage_proof(
+    query: form::Result<'_, StorageProofQuery>,
+    pool: &State<Pool>,
+    output_cache: &State<OutputCache>,
+    l2_rpc: &State<L2Rpc>,
+) -> Result<Json<StorageProofBundle>, ApiError> {
+    let query = query.map_err(|e| ApiError::InvalidParams(e.to_string()))?;
+    if !is_hex(&query.account, 20) || query.account.len() != 42 {
+        return Err(ApiError::InvalidParams(format!(
+            "account must be a 20 byte hex address, got {}",
+            query.account
+        )));
+    }
+    if query.slots.len() > MAX_PROOF_SLOTS {
+        return Err(ApiError::InvalidParams(format!(
+            "at most {} slots can be proven at once, got {}",
+            MAX_PROOF_SLOTS,
+            query.slots.len()
+        )));
+    }
+    if let Some(slot) = query.slots.iter().find(|slot| !is_hex(slot, 32)) {
+        return Err(ApiError::InvalidParams(format!(
+            "slot must be hex of at most 32 bytes, got {}",
+            slot
+        )));
+    }
+
+    let (network, _) = parse_network(&query.network)?;
+    if queries::is_arbitrum_stack(&network) {
+        return Err(ApiError::InvalidParams(format!(
+            "{} outputs do not commit to a state root, storage proofs are not supported",
+            network
+        )));
+    }
+
+    let output = crate::output_root(
+        &query.network,
+        query.l2_block,
+        LookupMode::Covering,
+        pool,
+        output_cache,
+    )
+    .await?;
+    let (output_root, l2_block_number) = match &output {
+        OutputType::OpStack(output) => {
+            (output.l2_output_root.clone(), output.l2_block_number as i64)
+        }
+        OutputType::OpStackFDG(output) => (output.root_claim.clone(), output.l2_block_number),
+        OutputType::Arbitrum(_) => {
+            return Err(ApiError::Internal(format!(
+                "unexpected Arbitrum output for {}",
+                network
+            )))
+        }
+    };
+
+    let url = L2Rpc::url(&query.network)?;
+    let (header, message_passer, proof) = tokio::try_join!(
+        l2_rpc.get_block_header(&url, l2_block_number),
+        l2_rpc.get_proof(&url, MESSAGE_PASSER_ADDRESS, &[], l2_block_number),
+        l2_rpc.get_proof(&url, &query.account, &query.slots, l2_block_number),
+    )?;
+    let preimage = OutputPreimage::new(
+        &output_root,
+        Some(header.state_root),
+        Some(message_passer.storage_hash),
+        Some(header.hash),
+    )
+    .ok_or_else(|| ApiError::Internal("incomplete output root preimage".to_string()))?;
+
+    Ok(Json(StorageProofBundle {
+        output: OutputRoot::new(&query.network, output),
+        preimage,
+        proof,
+    }))
+}
+
+#[derive(FromForm, IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+pub struct DisputeGamesQuery<'r> {
+    /// The network name, e.g. `optimism_mainnet`
+    network: String,
+    /// Only games with this status
+    #[param(inline, value_type = Option<GameStatus>)]
+    status: Option<form::Result<'r, GameStatus>>,
+    /// Only games of this type
+    #[param(value_type = Option<i64>)]
+    game_type: Option<form::Result<'r, i64>>,
+    /// Only games created by this address
+    proposer: Option<String>,
+    /// Only games claiming this L2 block or a later one
+    #[param(value_type = Option<i64>)]
+    from_l2_block: Option<form::Result<'r, i64>>,
+    /// Only games claiming this L2 block or an earlier one
+    #[param(value_type = Option<i64>)]
+    to_l2_block: Option<form::Result<'r, i64>>,
+    /// Only games whose root claim does (`true`) or does not (`false`) match the L2 block
+    #[param(value_type = Option<bool>)]
+    claim_verified: Option<form::Result<'r, bool>>,
+    /// The number of games per page, 100 by default and at most 1000
+    #[param(value_type = Option<i64>)]
+    limit: Option<form::Result<'r, i64>>,
+    /// The `next_cursor` of the previous page
+    #[param(value_type = Option<i64>)]
+    cursor: Option<form::Result<'r, i64>>,
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct DisputeGamePage {
+    pub items: Vec<DisputeGameDetail>,
+    /// Pass as `cursor` to get the next page, unset on the last page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<i64>,
+}
+
+/// Lists the dispute games of a network, newest first.
+///
+/// Unlike the output routes, every indexed game is listed, including the challenged and
+/// untrusted ones. `game_state` follows the on-chain resolution of the game: the indexer
+/// refreshes the games still in progress every `game_refresh_period_sec` of the network.
+#[utoipa::path(
+    get,
+    path = "/v1/dispute-games",
+    params(DisputeGamesQuery),
+    responses(
+        (status = 200, description = "A page of dispute games", body = DisputeGamePage),
+        (status = 400, description = "Unknown network", body = ErrorBody),
+        (status = 422, description = "Invalid query parameters, or a network without dispute games", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    )
+)]
+#[get("/dispute-games?<query..>")]
+async fn list_dispute_games(
+    query: form::Result<'_, DisputeGamesQuery<'_>>,
+    pool: &State<Pool>,
+) -> Result<Json<DisputeGamePage>, ApiError> {
+    let query = query.map_err(|e| ApiError::InvalidParams(e.to_string()))?;
+    let limit = optional_param("limit", query.limit)?.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        return Err(ApiError::InvalidParams(format!(
+            "limit must be between 1 and {}, got {}",
+            MAX_PAGE_SIZE, limit
+        )));
+    }
+
+    let (network, network_config) = parse_network(&query.network)?;
+    let filter = GameFilter {
+        status: optional_param("status", query.status)?,
+        game_type: optional_param("game_type", query.game_type)?,
+        proposer: query.proposer,
+        from_l2_block: optional_param("from_l2_block", query.from_l2_block)?,
+        to_l2_block: optional_param("to_l2_block", query.to_l2_block)?,
+        claim_verified: optional_param("claim_verified", query.claim_verified)?,
+    };
+    let cursor = optional_param("cursor", query.cursor)?;
+    let pg_client = db::get_client(pool).await?;
+    // Fetch one more game than asked to know whether there is a next page
+    let mut items = games::list_dispute_games(
+        &network,
+        &network_config,
+        &filter,
+        cursor,
+        limit + 1,
+        &pg_client,
+    )
+    .await?;
+
+    let has_more = items.len() as i64 > limit;
+    items.truncate(limit as usize);
+    let next_cursor = if has_more {
+        items.last().map(|game| game.game_index)
+    } else {
+        None
+    };
+
+    Ok(Json(DisputeGamePage { items, next_cursor }))
+}
+
+#[derive(FromForm, IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+pub struct DisputeGameQuery {
+    /// The network name, e.g. `optimism_mainnet`
+    network: String,
+}
+
+/// Returns a dispute game by its index in the DisputeGameFactory, whatever its status
+#[utoipa::path(
+    get,
+    path = "/v1/dispute-games/{game_index}",
+    params(
+        ("game_index" = i64, Path, description = "The index of the game in the DisputeGameFactory"),
+        DisputeGameQuery
+    ),
+    responses(
+        (status = 200, description = "The dispute game", body = DisputeGameDetail),
+        (status = 400, description = "Unknown network", body = ErrorBody),
+        (status = 404, description = "The game is not indexed", body = ErrorBody),
+        (status = 422, description = "Invalid query parameters, or a network without dispute games", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    )
+)]
+#[get("/dispute-games/<game_index>?<query..>")]
+async fn get_dispute_game(
+    game_index: i64,
+    query: form::Result<'_, DisputeGameQuery>,
+    pool: &State<Pool>,
+) -> Result<Json<DisputeGameDetail>, ApiError> {
+    let query = query.map_err(|e| ApiError::InvalidParams(e.to_string()))?;
+    let (network, network_config) = parse_network(&query.network)?;
+    let pg_client = db::get_client(pool).await?;
+    games::get_dispute_game(&network, &network_config, game_index, &pg_client)
+        .await
+        .map(Json)
+}
+
+#[derive(FromForm, IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+pub struct IndexerStatusQuery {
+    /// The network name, e.g. `optimism_mainnet`, all networks if unset
+    network: Option<String>,
+}
+
+/// The status of the indexer streams
+#[derive(Serialize, ToSchema, Debug)]
+pub struct IndexerStatusList {
+    pub items: Vec<StreamStatus>,
+}
+
+/// Returns the progress and health of every indexer stream of a network, or of all
+/// networks: the last L1 block indexed, the lag behind the L1 head, the last event
+/// indexed and the last error.
+#[utoipa::path(
+    get,
+    path = "/v1/status",
+    params(IndexerStatusQuery),
+    responses(
+        (status = 200, description = "The indexer streams, ordered by network and stream", body = IndexerStatusList),
+        (status = 400, description = "Unknown network", body = ErrorBody),
+        (status = 404, description = "No indexer ran for the network yet", body = ErrorBody),
+        (status = 422, description = "Invalid query parameters", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    )
+)]
+#[get("/status?<query..>")]
+async fn get_indexer_status(
+    query: form::Result<'_, IndexerStatusQuery>,
+    pool: &State<Pool>,
+) -> Result<Json<IndexerStatusList>, ApiError> {
+    let query = query.map_err(|e| ApiError::InvalidParams(e.to_string()))?;
+    let network = match &query.network {
+        Some(network) => Some(parse_network(network)?.0),
+        None => None,
+    };
+    let pg_client = db::get_client(pool).await?;
+    let items = status::get_indexer_status(network.as_ref(), &pg_client).await?;
+    if let (Some(network), true) = (network, items.is_empty()) {
+        return Err(ApiError::NetworkNotIndexed(network.to_string()));
+    }
+    Ok(Json(IndexerStatusList { items }))
+}
+
+/// Returns the highest L2 block with an indexed output root
+#[utoipa::path(
+    get,
+    path = "/v1/highest-l2-block",
+    params(NetworkQuery),
+    responses(
+        (status = 200, description = "The highest indexed L2 block", body = HighestL2Block),
+        (status = 400, description = "Unknown network", body = ErrorBody),
+        (status = 404, description = "Nothing is indexed for the network yet", body = ErrorBody),
+        (status = 422, description = "Invalid query parameters", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    )
+)]
+#[get("/highest-l2-block?<query..>")]
+async fn get_highest_l2_block(
+    query: form::Result<'_, NetworkQuery>,
+    pool: &State<Pool>,
+) -> Result<Json<HighestL2Block>, ApiError> {
+    let query = query.map_err(|e| ApiError::InvalidParams(e.to_string()))?;
+    let (network, network_config) = parse_network(&query.network)?;
+    let pg_client = db::get_client(pool).await?;
+    let highest = queries::get_highest_l2_block(&network, &network_config, &pg_client).await?;
+    Ok(Json(HighestL2Block {
+        network: highest.chain,
+        l2_block_number: highest.block_number.into(),
+    }))
+}
+
+/// Subscribes a URL to the events of some networks. Every event is POSTed as JSON and
+/// signed in `X-Webhook-Signature` with the returned `secret`, which is only shown once.
+#[utoipa::path(
+    post,
+    path = "/v1/webhooks",
+    request_body = WebhookRequest,
+    responses(
+        (status = 201, description = "The webhook, with its secret", body = Webhook),
+        (status = 400, description = "Unknown network", body = ErrorBody),
+        (status = 413, description = "Request body over the json limit", body = ErrorBody),
+        (status = 422, description = "Invalid request body", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    )
+)]
+#[post("/webhooks", format = "json", data = "<request>")]
+async fn post_webhook(
+    request: Result<Json<WebhookRequest>, json::Error<'_>>,
+    pool: &State<Pool>,
+) -> Result<Created<Json<Webhook>>, ApiError> {
+    let request = request.map_err(ApiError::from)?;
+    let pg_client = db::get_client(pool).await?;
+    let webhook = webhooks::create_webhook(request.into_inner(), &pg_client).await?;
+    Ok(Created::new(format!("/v1/webhooks/{}", webhook.id)).body(Json(webhook)))
+}
+
+/// Returns a webhook and the outcome of its last delivery
+#[utoipa::path(
+    get,
+    path = "/v1/webhooks/{id}",
+    params(("id" = String, Path, description = "The id returned when the webhook was created")),
+    responses(
+        (status = 200, description = "The webhook, without its secret", body = Webhook),
+        (status = 404, description = "No webhook has this id", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    )
+)]
+#[get("/webhooks/<id>")]
+async fn get_webhook(id: &str, pool: &State<Pool>) -> Result<Json<Webhook>, ApiError> {
+    let pg_client = db::get_client(pool).await?;
+    webhooks::get_webhook(id, &pg_client).await.map(Json)
+}
+
+/// Unsubscribes a webhook
+#[utoipa::path(
+    delete,
+    path = "/v1/webhooks/{id}",
+    params(("id" = String, Path, description = "The id returned when the webhook was created")),
+    responses(
+        (status = 204, description = "The webhook was deleted"),
+        (status = 404, description = "No webhook has this id", body = ErrorBody),
+        (status = 503, description = "Database unavailable", body = ErrorBody),
+    )
+)]
+#[delete("/webhooks/<id>")]
+async fn delete_webhook(id: &str, pool: &State<Pool>) -> Result<Status, ApiError> {
+    let pg_client = db::get_client(pool).await?;
+    webhooks::delete_webhook(id, &pg_client).await?;
+    Ok(Status::NoContent)
+}
+
+#[derive(FromForm, IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+pub struct EventsQuery {
+    /// A network to stream, e.g. `optimism_mainnet`, can be repeated. Every network is
+    /// streamed when unset
+    #[field(name = "network")]
+    #[param(rename = "network", required = false)]
+    networks: Vec<String>,
+    /// An event type to stream, can be repeated. Every type is streamed when unset
+    #[field(name = "type")]
+    #[param(rename = "type", required = false, value_type = Vec<LiveEventType>)]
+    types: Vec<String>,
+}
+
+/// Streams the outputs and dispute games stored by the indexer as Server-Sent Events,
+/// from the time of the request on. Each event is named after its `type`, has the `id`
+/// of the event and the JSON event as data.
+///
+/// A client that reads too slowly gets a `lagged` event with the number of `skipped`
+/// events, and should catch up with the other routes.
+#[utoipa::path(
+    get,
+    path = "/v1/events",
+    params(EventsQuery),
+    responses(
+        (status = 200, description = "A `text/event-stream` of the events", body = LiveEvent, content_type = "text/event-stream"),
+        (status = 400, description = "Unknown network", body = ErrorBody),
+        (status = 422, description = "Invalid query parameters", body = ErrorBody),
+    )
+)]
+#[get("/events?<query..>")]
+async fn stream_events(
+    query: form::Result<'_, EventsQuery>,
+    feed: &State<LiveFeed>,
+    mut shutdown: Shutdown,
+) -> Result<EventStream![], ApiError> {
+    let query = query.map_err(|e| ApiError::InvalidParams(e.to_string()))?;
+    let networks = query
+        .networks
+        .iter()
+        .map(|network| parse_network(network).map(|(network, _)| network.to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let types = query
+        .types
+        .iter()
+        .map(|kind| {
+            kind.parse()
+                .map_err(|e: eyre::Error| ApiError::InvalidParams(e.to_string()))
+        })
+        .collect::<Result<Vec<LiveEventType>, _>>()?;
+
+    let mut events = feed.subscribe();
+    Ok(EventStream! {
+        let _guard = StreamGuard::open();
+        loop {
+            let event = rocket::tokio::select! {
+                event = events.recv() => event,
+                _ = &mut shutdown => break,
+            };
+            match event {
+                Ok(event) => {
+                    if (networks.is_empty() || networks.contains(&event.network))
+                        && (types.is_empty() || types.contains(&event.kind))
+                    {
+                        yield Event::json(event.as_ref())
+                            .event(event.kind.as_str())
+                            .id(event.id.clone());
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    yield Event::json(&serde_json::json!({ "skipped": skipped })).event("lagged");
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+/// Returns this OpenAPI document
+#[utoipa::path(
+    get,
+    path = "/v1/openapi.json",
+    responses((status = 200, description = "The OpenAPI document of the v1 API"))
+)]
+#[get("/openapi.json")]
+fn get_openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
@@ -0,0 +1,945 @@
+use std::collections::HashSet;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ::metrics::counter;
+use common::{ChainName, ChainType, Network};
+use deadpool_postgres::Pool;
+use hmac::{Hmac, Mac};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::Url;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::{Orbit, Rocket};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::Sha256;
+use tokio_postgres::Row;
+use tracing::{debug, info, info_span, instrument, warn, Instrument};
+use utoipa::ToSchema;
+
+use crate::error::ApiError;
+use crate::games;
+use crate::queries::{self, parse_network, uses_fdg};
+
+/// The header carrying `t=<unix timestamp>,v1=<hex HMAC-SHA256 of "<t>.<body>">`
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+/// The header carrying the id of the event, the same for every attempt and replica
+pub const EVENT_ID_HEADER: &str = "X-Webhook-Id";
+/// Default seconds between two checks for new events, override with
+/// `WEBHOOK_POLL_INTERVAL_SEC`, 0 disables the deliveries
+const DEFAULT_POLL_INTERVAL_SEC: u64 = 15;
+/// Default attempts per event before giving up, override with `WEBHOOK_MAX_ATTEMPTS`
+const DEFAULT_MAX_ATTEMPTS: u32 = 6;
+/// How long a subscriber may take to answer an attempt
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+/// How often the outbox is checked for due deliveries
+const DELIVERY_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// How many due deliveries a replica claims at once
+const CLAIMED_DELIVERIES: i64 = 100;
+/// How long a claimed delivery is kept from the other replicas, longer than an attempt
+const CLAIM_LEASE: Duration = Duration::from_secs(60);
+/// The delay before the first retry, doubled after every attempt up to `MAX_RETRY_DELAY`
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+/// How many of the most recent dispute games are watched for becoming trusted
+const WATCHED_GAMES: i64 = 1000;
+/// The chains a subscription without network filter gets the events of
+const CHAIN_NAMES: [ChainName; 6] = [
+    ChainName::Arbitrum,
+    ChainName::ApeChain,
+    ChainName::Base,
+    ChainName::Optimism,
+    ChainName::Zora,
+    ChainName::WorldChain,
+];
+
+/// The events a webhook can subscribe to
+#[derive(Serialize, Deserialize, ToSchema, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    /// The highest L2 block with a usable output root advanced
+    HighestL2Block,
+    /// A dispute game became trusted by the output routes, either when it is created by
+    /// the trusted proposer or when it resolves as DEFENDER_WINS
+    DisputeGameValid,
+}
+
+impl WebhookEvent {
+    const ALL: [WebhookEvent; 2] = [WebhookEvent::HighestL2Block, WebhookEvent::DisputeGameValid];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::HighestL2Block => "highest_l2_block",
+            WebhookEvent::DisputeGameValid => "dispute_game_valid",
+        }
+    }
+}
+
+impl FromStr for WebhookEvent {
+    type Err = ApiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        WebhookEvent::ALL
+            .into_iter()
+            .find(|event| event.as_str() == s)
+            .ok_or_else(|| ApiError::Internal(format!("unknown webhook event {}", s)))
+    }
+}
+
+/// A webhook subscription
+#[derive(Deserialize, ToSchema, Debug)]
+pub struct WebhookRequest {
+    /// The http or https URL the events are POSTed to
+    pub url: String,
+    /// The networks to get the events of, e.g. `optimism_mainnet`, every network if empty
+    #[serde(default)]
+    pub networks: Vec<String>,
+    /// The events to get, every event if empty
+    #[serde(default)]
+    pub events: Vec<WebhookEvent>,
+}
+
+/// A webhook subscription and the outcome of its last delivery
+#[derive(Serialize, ToSchema, Clone, Debug)]
+pub struct Webhook {
+    pub id: String,
+    pub url: String,
+    /// Empty for every network
+    pub networks: Vec<String>,
+    pub events: Vec<WebhookEvent>,
+    /// The key the deliveries are signed with, only returned when the webhook is created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    /// When the webhook was created, as a unix timestamp
+    pub created_at: i64,
+    /// When an event was last delivered or given up on, as a unix timestamp
+    pub last_delivery_at: Option<i64>,
+    /// The HTTP status of the last attempt, unset when the URL could not be reached
+    pub last_status: Option<i32>,
+    /// Why the last event could not be delivered, cleared by a successful delivery
+    pub last_error: Option<String>,
+    /// Events given up on since the last successful delivery
+    pub consecutive_failures: i32,
+}
+
+impl Webhook {
+    fn from_row(row: &Row) -> Result<Self, ApiError> {
+        let events: Vec<String> = row.get("events");
+        Ok(Webhook {
+            id: row.get("id"),
+            url: row.get("url"),
+            networks: row.get("networks"),
+            events: events
+                .iter()
+                .map(|event| event.parse())
+                .collect::<Result<_, _>>()?,
+            secret: None,
+            created_at: row.get("created_at"),
+            last_delivery_at: row.get("last_delivery_at"),
+            last_status: row.get("last_status"),
+            last_error: row.get("last_error"),
+            consecutive_failures: row.get("consecutive_failures"),
+        })
+    }
+
+    fn wants(&self, network: &str, event: WebhookEvent) -> bool {
+        (self.networks.is_empty() || self.networks.iter().any(|n| n == network))
+            && self.events.contains(&event)
+    }
+}
+
+const WEBHOOK_COLUMNS: &str = "id, url, networks, events,
+        EXTRACT(EPOCH FROM created_at)::BIGINT AS created_at,
+        EXTRACT(EPOCH FROM last_delivery_at)::BIGINT AS last_delivery_at,
+        last_status, last_error, consecutive_failures";
+
+/// A function that validates a subscription and stores it.
+/// Parameters:
+/// * request: The subscription
+/// * pg_client: The postgres client
+///
+/// Returns:
+/// * The webhook, with the secret its deliveries are signed with
+#[instrument(name = "db.query", skip_all, fields(db.system = "postgresql"))]
+pub async fn create_webhook(
+    request: WebhookRequest,
+    pg_client: &tokio_postgres::Client,
+) -> Result<Webhook, ApiError> {
+    let url = Url::parse(&request.url)
+        .map_err(|e| ApiError::InvalidParams(format!("invalid url {}: {}", request.url, e)))?;
+    check_destination(&url)
+        .await
+        .map_err(|e| ApiError::InvalidParams(format!("url {} is refused: {}", request.url, e)))?;
+    let mut networks = Vec::new();
+    for network in request.networks.iter() {
+        let network = parse_network(network)?.0.to_string();
+        if !networks.contains(&network) {
+            networks.push(network);
+        }
+    }
+    let events: Vec<&str> = WebhookEvent::ALL
+        .iter()
+        .filter(|event| request.events.is_empty() || request.events.contains(event))
+        .map(WebhookEvent::as_str)
+        .collect();
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let secret = format!(
+        "whsec_{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    );
+    let insert_query = format!(
+        "INSERT INTO webhook_subscriptions (id, url, networks, events, secret)
+    VALUES ($1, $2, $3, $4, $5)
+    RETURNING {};",
+        WEBHOOK_COLUMNS
+    );
+    let row = pg_client
+        .query_one(
+            &insert_query,
+            &[&id, &url.as_str(), &networks, &events, &secret],
+        )
+        .await?;
+
+    let mut webhook = Webhook::from_row(&row)?;
+    webhook.secret = Some(secret);
+    Ok(webhook)
+}
+
+/// A function that returns a webhook by its id, without its secret
+#[instrument(name = "db.query", skip_all, fields(db.system = "postgresql", webhook_id = id))]
+pub async fn get_webhook(
+    id: &str,
+    pg_client: &tokio_postgres::Client,
+) -> Result<Webhook, ApiError> {
+    let select_query = format!(
+        "SELECT {} FROM webhook_subscriptions WHERE id = $1;",
+        WEBHOOK_COLUMNS
+    );
+    match pg_client.query_opt(&select_query, &[&id]).await? {
+        Some(row) => Webhook::from_row(&row),
+        None => Err(ApiError::WebhookNotFound(id.to_string())),
+    }
+}
+
+/// A function that deletes a webhook, its pending deliveries are dropped
+#[instrument(name = "db.query", skip_all, fields(db.system = "postgresql", webhook_id = id))]
+pub async fn delete_webhook(id: &str, pg_client: &tokio_postgres::Client) -> Result<(), ApiError> {
+    match pg_client
+        .execute("DELETE FROM webhook_subscriptions WHERE id = $1;", &[&id])
+        .await?
+    {
+        0 => Err(ApiError::WebhookNotFound(id.to_string())),
+        _ => Ok(()),
+    }
+}
+
+async fn list_subscriptions(pg_client: &tokio_postgres::Client) -> Result<Vec<Webhook>, ApiError> {
+    let select_query = format!("SELECT {} FROM webhook_subscriptions;", WEBHOOK_COLUMNS);
+    pg_client
+        .query(&select_query, &[])
+        .await?
+        .iter()
+        .map(Webhook::from_row)
+        .collect()
+}
+
+/// A delivery claimed from the `webhook_deliveries` outbox
+struct Delivery {
+    webhook_id: String,
+    event_id: String,
+    body: String,
+    /// The attempts made so far, this one included
+    attempts: i32,
+    url: String,
+    secret: String,
+}
+
+/// A function that claims the due deliveries. They are locked with `SKIP LOCKED`, so that
+/// each one is claimed by a single replica, and put off by `CLAIM_LEASE` so that they are
+/// tried again if the replica stops before their outcome is stored.
+/// Parameters:
+/// * pg_client: The postgres client
+///
+/// Returns:
+/// * Up to `CLAIMED_DELIVERIES` deliveries, oldest first
+async fn claim_deliveries(pg_client: &tokio_postgres::Client) -> Result<Vec<Delivery>, ApiError> {
+    let claim_query = format!(
+        "UPDATE webhook_deliveries AS d
+    SET attempts = d.attempts + 1,
+        next_attempt_at = now() + INTERVAL '{} seconds'
+    FROM webhook_subscriptions AS s
+    WHERE s.id = d.subscription_id
+    AND (d.subscription_id, d.event_id) IN (
+        SELECT subscription_id, event_id
+        FROM webhook_deliveries
+        WHERE next_attempt_at <= now()
+        ORDER BY next_attempt_at
+        LIMIT $1
+        FOR UPDATE SKIP LOCKED
+    )
+    RETURNING d.subscription_id, d.event_id, d.body, d.attempts, s.url, s.secret;",
+        CLAIM_LEASE.as_secs()
+    );
+    let rows = pg_client
+        .query(&claim_query, &[&CLAIMED_DELIVERIES])
+        .await?;
+    Ok(rows
+        .iter()
+        .map(|row| Delivery {
+            webhook_id: row.get("subscription_id"),
+            event_id: row.get("event_id"),
+            body: row.get("body"),
+            attempts: row.get("attempts"),
+            url: row.get("url"),
+            secret: row.get("secret"),
+        })
+        .collect())
+}
+
+/// A function that schedules the next attempt of a delivery
+async fn retry_delivery(pool: &Pool, delivery: &Delivery, delay: Duration) -> Result<(), ApiError> {
+    let pg_client = pool.get().await?;
+    pg_client
+        .execute(
+            "UPDATE webhook_deliveries
+    SET next_attempt_at = now() + $3 * INTERVAL '1 millisecond'
+    WHERE subscription_id = $1 AND event_id = $2;",
+            &[
+                &delivery.webhook_id,
+                &delivery.event_id,
+                &(delay.as_millis() as f64),
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+/// A function that removes a delivered or given up delivery from the outbox, and stores
+/// its outcome on the subscription
+async fn record_delivery(
+    pool: &Pool,
+    delivery: &Delivery,
+    status: Option<i32>,
+    error: Option<&str>,
+) -> Result<(), ApiError> {
+    let mut pg_client = pool.get().await?;
+    let transaction = pg_client.transaction().await?;
+    transaction
+        .execute(
+            "DELETE FROM webhook_deliveries WHERE subscription_id = $1 AND event_id = $2;",
+            &[&delivery.webhook_id, &delivery.event_id],
+        )
+        .await?;
+    transaction
+        .execute(
+            "UPDATE webhook_subscriptions
+    SET last_delivery_at = now(),
+        last_status = $2,
+        last_error = $3,
+        consecutive_failures = CASE WHEN $3::VARCHAR IS NULL THEN 0 ELSE consecutive_failures + 1 END
+    WHERE id = $1;",
+            &[&delivery.webhook_id, &status, &error],
+        )
+        .await?;
+    transaction.commit().await?;
+    Ok(())
+}
+
+/// The body POSTed to a webhook
+#[derive(Serialize, Clone, Debug)]
+struct EventPayload {
+    /// Derived from the event, so that retries and replicas can be deduplicated
+    id: String,
+    #[serde(rename = "type")]
+    event: WebhookEvent,
+    network: String,
+    /// When the service noticed the event, as a unix timestamp
+    created_at: u64,
+    data: serde_json::Value,
+}
+
+/// A function that signs a delivery with the secret of its webhook.
+/// Parameters:
+/// * secret: The secret returned when the webhook was created
+/// * timestamp: The unix timestamp of the attempt
+/// * body: The JSON body
+///
+/// Returns:
+/// * The hex HMAC-SHA256 of `<timestamp>.<body>`
+fn sign(secret: &str, timestamp: u64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// A function that tells whether an address is reachable from the internet, so that
+/// webhooks cannot be pointed at the loopback, private, link-local or unique local
+/// addresses of the network the service runs in.
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                // "This network", 0.0.0.0/8
+                || first == 0
+                // Carrier-grade NAT, 100.64.0.0/10
+                || (first == 100 && second & 0xc0 == 64))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ip(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_unspecified()
+                    || ip.is_loopback()
+                    || ip.is_multicast()
+                    // Unique local, fc00::/7
+                    || first & 0xfe00 == 0xfc00
+                    // Link-local, fe80::/10
+                    || first & 0xffc0 == 0xfe80)
+            }
+        },
+    }
+}
+
+/// A function that resolves a webhook host, refusing it if any of its addresses is not
+/// public.
+/// Parameters:
+/// * host: The domain name of the webhook URL
+/// * port: The port the addresses are returned with
+///
+/// Returns:
+/// * The addresses of the host, or why they are refused
+async fn resolve_public(host: &str, port: u16) -> Result<Vec<SocketAddr>, String> {
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| format!("{} does not resolve: {}", host, e))?
+        .collect();
+    if let Some(addr) = addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+        return Err(format!(
+            "{} resolves to the non public address {}",
+            host,
+            addr.ip()
+        ));
+    }
+    if addrs.is_empty() {
+        return Err(format!("{} has no address", host));
+    }
+    Ok(addrs)
+}
+
+/// A function that checks that a webhook URL is http or https and points at public
+/// addresses. Called when the webhook is created and before each delivery, as the
+/// records of its domain may have changed since.
+async fn check_destination(url: &Url) -> Result<(), String> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!(
+            "the scheme must be http or https, got {}",
+            url.scheme()
+        ));
+    }
+    let Some(host) = url.host_str() else {
+        return Err("the url has no host".to_string());
+    };
+    match host.trim_start_matches('[').trim_end_matches(']').parse() {
+        Ok(ip) if !is_public_ip(ip) => return Err(format!("{} is not a public address", ip)),
+        Ok(_) => {}
+        Err(_) => {
+            resolve_public(host, url.port_or_known_default().unwrap_or_default()).await?;
+        }
+    }
+    Ok(())
+}
+
+/// The resolver of the webhook client. It refuses the hosts with a non public address
+/// at connection time, so that a domain cannot pass [`check_destination`] and then be
+/// pointed at an internal address.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs = resolve_public(&host, 0).await?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Watches the subscribed networks every `WEBHOOK_POLL_INTERVAL_SEC` and POSTs the new
+/// events to the webhooks. Each delivery is retried with exponential backoff up to
+/// `WEBHOOK_MAX_ATTEMPTS` times.
+///
+/// Both the state the events are detected from and the pending deliveries are kept in
+/// PostgreSQL, so that events that happened while the service was down are sent after a
+/// restart, and that replicas send each event once between them.
+#[derive(Clone)]
+pub struct WebhookDispatcher {
+    poll_interval: Duration,
+    max_attempts: u32,
+}
+
+impl WebhookDispatcher {
+    pub fn from_env() -> eyre::Result<Self> {
+        let env_or = |key: &str, default: u64| -> eyre::Result<u64> {
+            match std::env::var(key) {
+                Ok(value) if !value.is_empty() => value
+                    .parse()
+                    .map_err(|e| eyre::eyre!("Invalid {}: {}", key, e)),
+                _ => Ok(default),
+            }
+        };
+        Ok(Self {
+            poll_interval: Duration::from_secs(env_or(
+                "WEBHOOK_POLL_INTERVAL_SEC",
+                DEFAULT_POLL_INTERVAL_SEC,
+            )?),
+            max_attempts: env_or("WEBHOOK_MAX_ATTEMPTS", DEFAULT_MAX_ATTEMPTS as u64)?.max(1)
+                as u32,
+        })
+    }
+
+    /// Queues the new events of the subscribed networks every `poll_interval`
+    async fn watch(self, pool: Pool) {
+        loop {
+            if let Err(e) = self.poll(&pool).await {
+                warn!(error = %e, "Failed to check for webhook events");
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    /// Looks for new events on the subscribed networks and queues their deliveries
+    async fn poll(&self, pool: &Pool) -> Result<(), ApiError> {
+        let pg_client = pool.get().await?;
+        let subscriptions = list_subscriptions(&pg_client).await?;
+        let networks: HashSet<String> = subscriptions
+            .iter()
+            .flat_map(|webhook| {
+                if webhook.networks.is_empty() {
+                    supported_networks()
+                } else {
+                    webhook.networks.clone()
+                }
+            })
+            .collect();
+        let networks: Vec<String> = networks.into_iter().collect();
+        // A network subscribed to again starts over from its state at that time
+        pg_client
+            .execute(
+                "DELETE FROM webhook_networks WHERE NOT (network = ANY($1));",
+                &[&networks],
+            )
+            .await?;
+
+        for network_name in networks {
+            if let Err(e) = poll_network(pool, &pg_client, &network_name, &subscriptions).await {
+                // Nothing is marked as seen, the next poll tries again
+                warn!(network = %network_name, error = %e, "Failed to check a network for webhook events");
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends the due deliveries every `DELIVERY_POLL_INTERVAL`
+    async fn send(self, pool: Pool, http: reqwest::Client) {
+        loop {
+            let claimed = match pool.get().await {
+                Ok(pg_client) => claim_deliveries(&pg_client).await,
+                Err(e) => Err(e.into()),
+            };
+            match claimed {
+                Ok(deliveries) => {
+                    for delivery in deliveries {
+                        let span = info_span!(
+                            "webhook.delivery",
+                            webhook_id = %delivery.webhook_id,
+                            event_id = %delivery.event_id
+                        );
+                        tokio::spawn(
+                            deliver(pool.clone(), http.clone(), delivery, self.max_attempts)
+                                .instrument(span),
+                        );
+                    }
+                }
+                Err(e) => warn!(error = %e, "Failed to claim the webhook deliveries"),
+            }
+            tokio::time::sleep(DELIVERY_POLL_INTERVAL).await;
+        }
+    }
+}
+
+/// A function that compares a network with what was last seen of it, and queues the
+/// deliveries of its new events. The row of the network is locked until the events are
+/// queued, so that replicas polling at the same time do not queue them twice.
+/// Parameters:
+/// * pool: The PostgreSQL pool, the state of the network is updated in a transaction
+/// * pg_client: The postgres client the indexed data is read with
+/// * network_name: The network, e.g. `optimism_mainnet`
+/// * subscriptions: Every webhook subscription
+///
+/// Returns:
+/// Returns nothing except for error
+async fn poll_network(
+    pool: &Pool,
+    pg_client: &tokio_postgres::Client,
+    network_name: &str,
+    subscriptions: &[Webhook],
+) -> Result<(), ApiError> {
+    let (network, network_config) = parse_network(network_name)?;
+    let mut state_client = pool.get().await?;
+    let transaction = state_client.transaction().await?;
+    transaction
+        .execute(
+            "INSERT INTO webhook_networks (network) VALUES ($1) ON CONFLICT (network) DO NOTHING;",
+            &[&network_name],
+        )
+        .await?;
+    let state = transaction
+        .query_one(
+            "SELECT highest_l2_block, trusted_games FROM webhook_networks WHERE network = $1 FOR UPDATE;",
+            &[&network_name],
+        )
+        .await?;
+    let seen_highest_l2_block: Option<i64> = state.get("highest_l2_block");
+    let seen_trusted_games: Option<Vec<i64>> = state.get("trusted_games");
+    let mut events = Vec::new();
+
+    let mut highest_l2_block = seen_highest_l2_block;
+    match queries::get_highest_l2_block(&network, &network_config, pg_client).await {
+        Ok(highest) => {
+            let block_number = highest.block_number as i64;
+            if let Some(previous) = seen_highest_l2_block.filter(|b| block_number > *b) {
+                events.push(EventPayload {
+                    id: format!("{}:highest_l2_block:{}", network_name, block_number),
+                    event: WebhookEvent::HighestL2Block,
+                    network: network_name.to_string(),
+                    created_at: unix_now(),
+                    data: json!({
+                        "l2_block_number": block_number,
+                        "previous_l2_block_number": previous,
+                    }),
+                });
+            }
+            highest_l2_block = Some(block_number);
+        }
+        Err(e) => debug!(network = %network, error = %e, "No highest L2 block yet"),
+    }
+
+    let mut trusted_games = seen_trusted_games.clone();
+    if uses_fdg(&network) {
+        match games::list_trusted_game_indexes(&network, &network_config, WATCHED_GAMES, pg_client)
+            .await
+        {
+            Ok(game_indexes) => {
+                if let Some(previous) = &seen_trusted_games {
+                    let previous: HashSet<i64> = previous.iter().copied().collect();
+                    let mut new_games: Vec<i64> = game_indexes
+                        .iter()
+                        .copied()
+                        .filter(|game_index| !previous.contains(game_index))
+                        .collect();
+                    new_games.sort_unstable();
+                    events.extend(game_events(network_name, &new_games, pg_client).await?);
+                }
+                trusted_games = Some(game_indexes);
+            }
+            Err(e) => debug!(network = %network, error = %e, "No dispute games yet"),
+        }
+    }
+
+    for event in events {
+        let subscribers: Vec<&str> = subscriptions
+            .iter()
+            .filter(|webhook| webhook.wants(&event.network, event.event))
+            .map(|webhook| webhook.id.as_str())
+            .collect();
+        let body = serde_json::to_string(&event).map_err(|e| ApiError::Internal(e.to_string()))?;
+        // The subscriptions deleted since they were listed are skipped
+        transaction
+            .execute(
+                "INSERT INTO webhook_deliveries (subscription_id, event_id, body)
+    SELECT id, $2, $3 FROM webhook_subscriptions WHERE id = ANY($1)
+    ON CONFLICT (subscription_id, event_id) DO NOTHING;",
+                &[&subscribers, &event.id, &body],
+            )
+            .await?;
+        info!(event_id = %event.id, subscribers = subscribers.len(), "New webhook event");
+    }
+    transaction
+        .execute(
+            "UPDATE webhook_networks
+    SET highest_l2_block = $2, trusted_games = $3, updated_at = now()
+    WHERE network = $1;",
+            &[&network_name, &highest_l2_block, &trusted_games],
+        )
+        .await?;
+    transaction.commit().await?;
+    Ok(())
+}
+
+/// A function that builds the `dispute_game_valid` events of the games that became trusted.
+/// Parameters:
+/// * network_name: The network of the games, e.g. `optimism_mainnet`
+/// * game_indexes: The indexes of the games
+/// * pg_client: The postgres client
+///
+/// Returns:
+/// * One event per game, in the order of the indexes
+async fn game_events(
+    network_name: &str,
+    game_indexes: &[i64],
+    pg_client: &tokio_postgres::Client,
+) -> Result<Vec<EventPayload>, ApiError> {
+    let (network, network_config) = parse_network(network_name)?;
+    let mut events = Vec::with_capacity(game_indexes.len());
+    for game_index in game_indexes.iter().copied() {
+        let game =
+            games::get_dispute_game(&network, &network_config, game_index, pg_client).await?;
+        events.push(EventPayload {
+            id: format!("{}:dispute_game_valid:{}", network_name, game_index),
+            event: WebhookEvent::DisputeGameValid,
+            network: network_name.to_string(),
+            created_at: unix_now(),
+            data: serde_json::to_value(game).map_err(|e| ApiError::Internal(e.to_string()))?,
+        });
+    }
+    Ok(events)
+}
+
+#[rocket::async_trait]
+impl Fairing for WebhookDispatcher {
+    fn info(&self) -> Info {
+        Info {
+            name: "Webhook dispatcher",
+            kind: Kind::Liftoff,
+        }
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        if self.poll_interval.is_zero() {
+            info!("Webhook deliveries are disabled");
+            return;
+        }
+        let Some(pool) = rocket.state::<Pool>().cloned() else {
+            warn!("No PostgreSQL pool, webhooks are disabled");
+            return;
+        };
+        // Redirects and proxies would reach addresses the destination checks never saw
+        let http = match reqwest::Client::builder()
+            .timeout(DELIVERY_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::none())
+            .no_proxy()
+            .dns_resolver(Arc::new(PublicResolver))
+            .build()
+        {
+            Ok(http) => http,
+            Err(e) => {
+                warn!(error = %e, "Failed to build the webhook client, webhooks are disabled");
+                return;
+            }
+        };
+        tokio::spawn(self.clone().watch(pool.clone()));
+        tokio::spawn(self.clone().send(pool, http));
+    }
+}
+
+/// A function that returns the delay before the attempt following `attempt`, starting at
+/// `FIRST_RETRY_DELAY` and doubled after every attempt up to `MAX_RETRY_DELAY`
+fn retry_delay(attempt: u32) -> Duration {
+    FIRST_RETRY_DELAY
+        .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+        .min(MAX_RETRY_DELAY)
+}
+
+/// A function that POSTs a claimed delivery to its webhook. A 2xx status or the last of
+/// `max_attempts` attempts removes it from the outbox and stores the outcome on the
+/// subscription, other failures schedule the next attempt. Other 4xx statuses than 408
+/// and 429 are not retried.
+async fn deliver(pool: Pool, http: reqwest::Client, delivery: Delivery, max_attempts: u32) {
+    let attempt = delivery.attempts.max(1) as u32;
+    let destination = match Url::parse(&delivery.url) {
+        Ok(url) => check_destination(&url).await,
+        Err(e) => Err(e.to_string()),
+    };
+    let (status, error, retry) = match destination {
+        Err(e) => (None, Some(format!("the url is refused: {}", e)), false),
+        Ok(()) => {
+            let timestamp = unix_now();
+            let result = http
+                .post(&delivery.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(
+                    SIGNATURE_HEADER,
+                    format!(
+                        "t={},v1={}",
+                        timestamp,
+                        sign(&delivery.secret, timestamp, delivery.body.as_bytes())
+                    ),
+                )
+                .header(EVENT_ID_HEADER, &delivery.event_id)
+                .body(delivery.body.clone())
+                .send()
+                .await;
+            match result {
+                Ok(response) if response.status().is_success() => {
+                    (Some(response.status().as_u16() as i32), None, false)
+                }
+                Ok(response) => {
+                    let status = response.status();
+                    (
+                        Some(status.as_u16() as i32),
+                        Some(format!("the webhook answered {}", status)),
+                        status.is_server_error()
+                            || status == reqwest::StatusCode::REQUEST_TIMEOUT
+                            || status == reqwest::StatusCode::TOO_MANY_REQUESTS,
+                    )
+                }
+                Err(e) => (None, Some(e.to_string()), true),
+            }
+        }
+    };
+
+    if let Some(error) = error.as_deref() {
+        warn!(attempt, status, error, "Webhook delivery failed");
+        if retry && attempt < max_attempts {
+            if let Err(e) = retry_delivery(&pool, &delivery, retry_delay(attempt)).await {
+                warn!(error = %e, "Failed to schedule the next webhook attempt");
+            }
+            return;
+        }
+    }
+
+    counter!(
+        "webhook_deliveries_total",
+        "result" => if error.is_none() { "delivered" } else { "failed" }
+    )
+    .increment(1);
+    match error.as_deref() {
+        None => debug!(status, "Webhook event delivered"),
+        Some(error) => warn!(status, error, "Gave up on the webhook event"),
+    }
+    if let Err(e) = record_delivery(&pool, &delivery, status, error.as_deref()).await {
+        warn!(error = %e, "Failed to store the webhook delivery");
+    }
+}
+
+/// A function that returns every network the service has a config for
+fn supported_networks() -> Vec<String> {
+    CHAIN_NAMES
+        .into_iter()
+        .flat_map(|chain_name| {
+            [ChainType::Mainnet, ChainType::Sepolia].map(|chain_type| Network {
+                chain_name,
+                chain_type,
+            })
+        })
+        .map(|network| network.to_string())
+        .filter(|network| parse_network(network).is_ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_is_the_hmac_of_the_timestamp_and_body() {
+        let body = br#"{"id":"x"}"#;
+        assert_eq!(
+            sign("whsec_test", 1_700_000_000, body),
+            "80e8d098018f757aa814ab1abb924f35d304affa1d93d29611a54696207eb7c3"
+        );
+        // Replaying the body with another timestamp or key does not verify
+        assert_ne!(
+            sign("whsec_test", 1_700_000_001, body),
+            sign("whsec_test", 1_700_000_000, body)
+        );
+        assert_ne!(
+            sign("whsec_other", 1_700_000_000, body),
+            sign("whsec_test", 1_700_000_000, body)
+        );
+    }
+
+    #[test]
+    fn internal_addresses_are_not_public() {
+        for ip in [
+            "0.0.0.0",
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "255.255.255.255",
+            "::",
+            "::1",
+            "fc00::1",
+            "fd12:3456::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::ffff:169.254.169.254",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[test]
+    fn internet_addresses_are_public() {
+        for ip in [
+            "1.1.1.1",
+            "8.8.8.8",
+            "100.128.0.1",
+            "2606:4700::1111",
+            "::ffff:8.8.8.8",
+        ] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[test]
+    fn retries_back_off_exponentially_up_to_a_minute() {
+        let delays: Vec<u64> = (1..=8)
+            .map(|attempt| retry_delay(attempt).as_secs())
+            .collect();
+        assert_eq!(delays, [1, 2, 4, 8, 16, 32, 60, 60]);
+        assert_eq!(retry_delay(u32::MAX), MAX_RETRY_DELAY);
+    }
+
+    #[tokio::test]
+    async fn webhooks_to_internal_hosts_are_refused() {
+        for url in [
+            "http://127.0.0.1:8000/hook",
+            "http://2130706433/hook",
+            "http://[::1]/hook",
+            "http://[::ffff:10.0.0.1]/hook",
+            "https://169.254.169.254/latest/meta-data",
+            "http://localhost:8000/hook",
+            "ftp://example.com/hook",
+        ] {
+            let url = Url::parse(url).unwrap();
+            assert!(check_destination(&url).await.is_err(), "{url}");
+        }
+        let url = Url::parse("https://1.1.1.1/hook").unwrap();
+        assert!(check_destination(&url).await.is_ok());
+    }
+}
@@ -1,73 +1,25 @@
+use crate::error::IndexerError;
 use crate::fetcher::Fetcher;
+use crate::logs::{decode_log, L1LogMeta};
+use crate::telemetry;
 use ::common::ChainType;
 use common::ChainName;
 use ethers::prelude::*;
-use eyre::Result;
+use tokio_postgres::GenericClient;
+use tracing::info;
+
+abigen!(ArbitrumOutbox, "abi/ArbitrumOutbox.json");
 
 pub struct ArbitrumParameters {
-    l2_output_root: Bytes,
+    pub(crate) l2_output_root: Bytes,
     l2_block_hash: Bytes,
-    l2_block_number: U256,
-    l1_transaction_hash: Bytes,
-    l1_block_number: U64,
+    pub(crate) l2_block_number: U256,
+    pub(crate) l1_transaction_hash: Bytes,
+    pub(crate) l1_block_number: U64,
     l1_transaction_index: U64,
     l1_block_hash: Bytes,
 }
 
-/// A function that creates a table if it doesn't exist, and returns the max block number in the table if it exists.
-/// Parameters:
-/// * table_name: The name of the postgres table
-/// * client: The postgres client
-/// Returns:
-/// * Option<i32>: The max block number in the table if it exists, otherwise None
-pub async fn create_arbitrum_table_if_not_exists(
-    table_name: String,
-    client: &tokio_postgres::Client,
-) -> Result<Option<i32>, tokio_postgres::Error> {
-    let create_table_query = format!("SELECT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_name = '{}') AS table_existence;", table_name);
-    let rows = client.query(&create_table_query, &[]).await?;
-
-    // And then check that we got back the same string we sent over.
-    let exist: bool = rows[0].get(0);
-    println!("Table exist : {exist}");
-    if exist {
-        // Query the maximum l1_block_number
-        let create_table_query = format!(
-            "SELECT MAX(l1_block_number) as MaxBlock from {}",
-            table_name
-        );
-        let rows = client.query(&create_table_query, &[]).await?;
-
-        // Handle possible NULL result for max l1_block_number
-        let max_blocknum: Option<i32> = rows[0].try_get(0)?;
-
-        if let Some(max_num) = max_blocknum {
-            println!("max_blocknum: {max_num}");
-            Ok(Some(max_num))
-        } else {
-            println!("No entries in the table, hence no maximum block number.");
-            Ok(None)
-        }
-    } else {
-        let create_table_query = format!(
-            "CREATE TABLE IF NOT EXISTS {} (
-                id                      SERIAL PRIMARY KEY,
-                l2_output_root          VARCHAR NOT NULL,
-                l2_block_hash           VARCHAR NOT NULL,
-                l2_block_number         INTEGER NOT NULL,
-                l1_transaction_hash     VARCHAR NOT NULL,
-                l1_block_number         INTEGER NOT NULL,
-                l1_transaction_index    INTEGER NOT NULL,
-                l1_block_hash           VARCHAR NOT NULL
-            )",
-            table_name
-        );
-        client.execute(&create_table_query, &[]).await?;
-
-        Ok(None)
-    }
-}
-
 /// A function that inserts data into the postgres table
 /// Parameters:
 /// * table_name: The name of the postgres table
@@ -80,13 +32,14 @@ pub async fn create_arbitrum_table_if_not_exists(
 /// * l1_block_number: The block number of the l1
 /// * l1_transaction_index: The transaction index of the l1
 /// * l1_block_hash: The block hash of the l1
+///
 /// Returns:
 /// Returns nothing except for error
 pub async fn insert_into_postgres(
     table_name: String,
-    client: &tokio_postgres::Client,
+    client: &impl GenericClient,
     params: ArbitrumParameters,
-) -> Result<()> {
+) -> Result<(), tokio_postgres::Error> {
     let insert_query = format!("INSERT INTO {} (l2_output_root, l2_block_hash, l2_block_number, l1_transaction_hash, l1_block_number, l1_transaction_index, l1_block_hash) VALUES ($1, $2, $3, $4, $5, $6, $7)", table_name);
     client
         .execute(
@@ -108,30 +61,40 @@ pub async fn insert_into_postgres(
 
 pub async fn handle_arbitrum_events(
     log: &Log,
-    chain_name: &ChainName,
-    chain_type: &ChainType,
-) -> Result<ArbitrumParameters> {
+    arbitrum_fetcher: &Fetcher,
+) -> Result<ArbitrumParameters, IndexerError> {
     //? Example log : log = Log { address: 0x0b9857ae2d4a3dbe74ffe1d7df045bb7f96e4840, topics: [0xb4df3847300f076a369cd76d2314b470a1194d9e8a6bb97f1860aee88a5f6748, 0x46ac12a9031cfe15b510a19b1ee6a237409cb5659fba8a71192229f7d086e67f, 0xf4369a47ee900d312913d8cb382a4eb174272c42cead9cdaf8c4db9b5f0eb9e9], data: Bytes(0x), block_hash: Some(0x0bf39cb7a1ef70be6350438c8e99a22e785d46309c91aaaf65d760e92ed97bd7), block_number: Some(15843456), transaction_hash: Some(0x306ce7c969f40a8afc7dc2fa0a45ba13daee06fecbb1ed938c129749225a0963), transaction_index: Some(188), log_index: Some(323), transaction_log_index: None, log_type: None, removed: Some(false) }
-    let l2_output_root = Bytes::from(log.topics[1].as_bytes().to_vec());
-    let l2_block_hash = Bytes::from(log.topics[2].as_bytes().to_vec());
-    let l1_transaction_hash = Bytes::from(log.transaction_hash.unwrap().as_bytes().to_vec());
-    let l1_block_number = log.block_number.unwrap();
-    let l1_transaction_index = log.transaction_index.unwrap();
-    let l1_block_hash = Bytes::from(log.block_hash.unwrap().as_bytes().to_vec());
-    let arbitrum_rpc_url = get_arb_stack_rpc_url(chain_name, chain_type);
+    let event: SendRootUpdatedFilter = decode_log(log)?;
+    let meta = L1LogMeta::from_log(log)?;
 
-    let arbitrum_fetcher = Fetcher::new(arbitrum_rpc_url.to_string());
+    let l2_output_root = Bytes::from(event.output_root.to_vec());
+    let l2_block_hash = Bytes::from(event.l_2_block_hash.to_vec());
+    let l1_transaction_hash = meta.transaction_hash;
+    let l1_block_number = meta.block_number;
+    let l1_transaction_index = meta.transaction_index;
+    let l1_block_hash = meta.block_hash;
 
-    let block = arbitrum_fetcher
-        .fetch_block_by_hash(&l2_block_hash.to_string())
-        .await?;
+    let block = telemetry::observe_rpc(
+        "eth_getBlockByHash",
+        arbitrum_fetcher.fetch_block_by_hash(&l2_block_hash.to_string()),
+    )
+    .await
+    .map_err(|e| IndexerError::Rpc(format!("eth_getBlockByHash({l2_block_hash}): {e}")))?;
 
-    let dec_number = u64::from_str_radix(&block.number.as_str()[2..], 16).unwrap();
+    let dec_number =
+        u64::from_str_radix(block.number.trim_start_matches("0x"), 16).map_err(|e| {
+            IndexerError::Decode(format!("invalid L2 block number {:?}: {e}", block.number))
+        })?;
 
     let l2_block_number: U256 = U256::from(dec_number);
-    println!(
-                "output_root = {l2_output_root}, l2blockhash = {l2_block_hash}, l2_block_number = {l2_block_number}, l1Blocknumber = {l1_block_number}, l1_transaction_hash={l1_transaction_hash}, l1_transaction_index={l1_transaction_index}, L1_block_hash={l1_block_hash}",
-            );
+    info!(
+        %l2_output_root,
+        %l2_block_hash,
+        %l2_block_number,
+        %l1_block_hash,
+        l1_transaction_index = l1_transaction_index.as_u64(),
+        "Decoded SendRootUpdated"
+    );
 
     Ok(ArbitrumParameters {
         l2_output_root,
@@ -144,19 +107,29 @@ pub async fn handle_arbitrum_events(
     })
 }
 
-fn get_arb_stack_rpc_url(chain_name: &ChainName, chain_type: &ChainType) -> String {
-    match (chain_name, chain_type) {
-        (ChainName::Arbitrum, ChainType::Mainnet) => std::env::var("ARBITRUM_MAINNET_RPC_URL")
-            .expect("ARBITRUM_MAINNET_RPC_URL must be set."),
-        (ChainName::Arbitrum, ChainType::Sepolia) => std::env::var("ARBITRUM_SEPOLIA_RPC_URL")
-            .expect("ARBITRUM_SEPOLIA_RPC_URL must be set."),
-        (ChainName::Arbitrum, ChainType::Goerli) => {
-            std::env::var("ARBITRUM_GOERLI_RPC_URL").expect("ARBITRUM_GOERLI_RPC_URL must be set.")
+/// A function that returns the L2 RPC URL used to resolve Arbitrum block hashes.
+/// Parameters:
+/// * chain_name: The chain name (arbitrum or ape_chain)
+/// * chain_type: The chain type (mainnet, sepolia, goerli)
+///
+/// Returns:
+/// * The RPC URL, or a configuration error if the variable is not set
+pub fn get_arb_stack_rpc_url(
+    chain_name: &ChainName,
+    chain_type: &ChainType,
+) -> Result<String, IndexerError> {
+    let var_name = match (chain_name, chain_type) {
+        (ChainName::Arbitrum, ChainType::Mainnet) => "ARBITRUM_MAINNET_RPC_URL",
+        (ChainName::Arbitrum, ChainType::Sepolia) => "ARBITRUM_SEPOLIA_RPC_URL",
+        (ChainName::Arbitrum, ChainType::Goerli) => "ARBITRUM_GOERLI_RPC_URL",
+        (ChainName::ApeChain, ChainType::Mainnet) => "APE_CHAIN_MAINNET_RPC_URL",
+        (ChainName::ApeChain, ChainType::Sepolia) => "APE_CHAIN_SEPOLIA_RPC_URL",
+        _ => {
+            return Err(IndexerError::Config(format!(
+                "{chain_name}_{chain_type} is not an Arbitrum stack network"
+            )))
         }
-        (ChainName::ApeChain, ChainType::Mainnet) => std::env::var("APE_CHAIN_MAINNET_RPC_URL")
-            .expect("APE_CHAIN_MAINNET_RPC_URL must be set."),
-        (ChainName::ApeChain, ChainType::Sepolia) => std::env::var("APE_CHAIN_SEPOLIA_RPC_URL")
-            .expect("APE_CHAIN_SEPOLIA_RPC_URL must be set."),
-        _ => panic!("Invalid chain name or chain type"),
-    }
+    };
+
+    std::env::var(var_name).map_err(|_| IndexerError::Config(format!("{var_name} must be set.")))
 }
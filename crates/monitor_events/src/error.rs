@@ -0,0 +1,137 @@
+use ethers::prelude::*;
+use std::time::Duration;
+use tokio_postgres::error::SqlState;
+
+/// Errors raised while indexing a stream.
+///
+/// Transient errors (RPC and database connectivity) are retried in place, permanent
+/// errors (malformed logs, broken invariants, bad configuration) halt the stream
+/// because retrying them cannot succeed and skipping them would corrupt the index.
+#[derive(Debug, thiserror::Error)]
+pub enum IndexerError {
+    #[error("RPC error: {0}")]
+    Rpc(String),
+    #[error("database error: {0}")]
+    Database(#[from] tokio_postgres::Error),
+    #[error("failed to decode event: {0}")]
+    Decode(String),
+    #[error("invariant violated: {0}")]
+    Invariant(String),
+    #[error("configuration error: {0}")]
+    Config(String),
+}
+
+impl IndexerError {
+    /// Whether retrying the failed operation may succeed
+    pub fn is_transient(&self) -> bool {
+        match self {
+            IndexerError::Rpc(_) => true,
+            IndexerError::Database(err) => is_transient_db_error(err),
+            IndexerError::Decode(_) | IndexerError::Invariant(_) | IndexerError::Config(_) => false,
+        }
+    }
+
+    /// Classifies a failed contract call: reverts and undecodable return data are
+    /// permanent, everything else is an RPC failure.
+    pub fn from_contract_call<M: Middleware>(method: &str, err: ContractError<M>) -> Self {
+        match err {
+            ContractError::Revert(data) => {
+                IndexerError::Invariant(format!("{method} reverted with data {data}"))
+            }
+            ContractError::DecodingError(_)
+            | ContractError::AbiError(_)
+            | ContractError::DetokenizationError(_) => {
+                IndexerError::Decode(format!("{method} returned undecodable data: {err}"))
+            }
+            _ => IndexerError::Rpc(format!("{method} failed: {err}")),
+        }
+    }
+}
+
+impl From<ProviderError> for IndexerError {
+    fn from(err: ProviderError) -> Self {
+        IndexerError::Rpc(err.to_string())
+    }
+}
+
+/// Connection level failures carry no SQLSTATE; otherwise only connection, resource
+/// and serialization classes are worth retrying.
+fn is_transient_db_error(err: &tokio_postgres::Error) -> bool {
+    err.is_closed() || is_transient_sqlstate(err.code())
+}
+
+fn is_transient_sqlstate(state: Option<&SqlState>) -> bool {
+    match state {
+        None => true,
+        Some(state) => {
+            let code = state.code();
+            code.starts_with("08")
+                || code.starts_with("53")
+                || code.starts_with("57")
+                || code == "40001"
+                || code == "40P01"
+        }
+    }
+}
+
+/// Exponential backoff used when retrying transient errors in place
+pub struct Backoff {
+    next: Duration,
+    max: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            next: Duration::from_secs(1),
+            max: Duration::from_secs(60),
+        }
+    }
+}
+
+impl Backoff {
+    /// Sleeps for the current delay and doubles it, up to `max`
+    pub async fn wait(&mut self) {
+        tokio::time::sleep(self.next).await;
+        self.next = (self.next * 2).min(self.max);
+    }
+
+    pub fn next_delay(&self) -> Duration {
+        self.next
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connection_resource_and_serialization_errors_are_transient() {
+        for code in ["08006", "08001", "53300", "57P01", "40001", "40P01"] {
+            assert!(
+                is_transient_sqlstate(Some(&SqlState::from_code(code))),
+                "{code}"
+            );
+        }
+        // Errors without a SQLSTATE never reached the server
+        assert!(is_transient_sqlstate(None));
+    }
+
+    #[test]
+    fn query_errors_are_permanent() {
+        for code in ["23505", "42P01", "42703", "22003", "40002"] {
+            assert!(
+                !is_transient_sqlstate(Some(&SqlState::from_code(code))),
+                "{code}"
+            );
+        }
+    }
+
+    #[test]
+    fn only_rpc_errors_are_transient_outside_the_database() {
+        assert!(IndexerError::Rpc("timeout".to_string()).is_transient());
+        assert!(!IndexerError::Decode("bad log".to_string()).is_transient());
+        assert!(!IndexerError::Invariant("gap".to_string()).is_transient());
+        assert!(!IndexerError::Config("missing".to_string()).is_transient());
+    }
+}
@@ -0,0 +1,128 @@
+use ethers::prelude::*;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+/// Delay before trying to re-establish a dropped `newHeads` subscription.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Decides when the indexing loop runs its next iteration.
+///
+/// In polling mode the loop simply waits `poll_period`. In subscription mode a
+/// background task follows `newHeads` over WebSocket and wakes the loop as soon as
+/// a new L1 head arrives; `poll_period` is still used as an upper bound so the loop
+/// keeps polling while the subscription is down or reconnecting.
+pub struct HeadWatcher {
+    poll_period: Duration,
+    min_interval: Duration,
+    heads: Option<watch::Receiver<U64>>,
+}
+
+impl HeadWatcher {
+    /// A watcher that only sleeps `poll_period` between iterations
+    pub fn polling(poll_period: Duration) -> Self {
+        Self {
+            poll_period,
+            min_interval: Duration::ZERO,
+            heads: None,
+        }
+    }
+
+    /// A watcher that wakes on new L1 heads received over `ws_url`.
+    /// Parameters:
+    /// * ws_url: The WebSocket RPC URL of the L1 (settlement) chain
+    /// * poll_period: The fallback polling period used while the subscription is down
+    /// * min_interval: The minimum time between two iterations, for fast L1s
+    pub fn subscribe(ws_url: String, poll_period: Duration, min_interval: Duration) -> Self {
+        let (tx, rx) = watch::channel(U64::zero());
+        tokio::spawn(follow_heads(ws_url, tx));
+        Self {
+            poll_period,
+            min_interval,
+            heads: Some(rx),
+        }
+    }
+
+    pub fn is_subscribed(&self) -> bool {
+        self.heads.is_some()
+    }
+
+    /// Waits until the next iteration should run: either a new head was seen or
+    /// `poll_period` elapsed, whichever comes first.
+    pub async fn wait(&mut self) {
+        let Some(heads) = self.heads.as_mut() else {
+            tokio::time::sleep(self.poll_period).await;
+            return;
+        };
+
+        tokio::time::sleep(self.min_interval).await;
+        tokio::select! {
+            changed = heads.changed() => {
+                if changed.is_err() {
+                    // The subscription task is gone for good, keep going in polling mode
+                    warn!("newHeads subscription task stopped, falling back to polling");
+                    self.heads = None;
+                }
+            }
+            _ = tokio::time::sleep(self.poll_period.saturating_sub(self.min_interval)) => {}
+        }
+    }
+}
+
+/// Keeps a `newHeads` subscription alive and publishes every head number to `tx`,
+/// reconnecting after `RECONNECT_DELAY` whenever the socket drops.
+async fn follow_heads(ws_url: String, tx: watch::Sender<U64>) {
+    let ws_host = url_host(&ws_url);
+    loop {
+        match Provider::<Ws>::connect(ws_url.as_str()).await {
+            Ok(provider) => match provider.subscribe_blocks().await {
+                Ok(mut stream) => {
+                    info!(ws_host, "Subscribed to newHeads");
+                    while let Some(block) = stream.next().await {
+                        if let Some(number) = block.number {
+                            if tx.send(number).is_err() {
+                                // The indexing loop is gone, nothing left to wake up
+                                return;
+                            }
+                        }
+                    }
+                    warn!("newHeads subscription closed, polling until it is restored");
+                }
+                Err(e) => warn!(error = ?e, "Failed to subscribe to newHeads"),
+            },
+            Err(e) => warn!(ws_host, error = ?e, "Failed to connect to the WebSocket RPC"),
+        }
+
+        if tx.is_closed() {
+            return;
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+/// A function that returns the host of an RPC URL, which is safe to log unlike the
+/// path and query that often carry the API key of the provider.
+fn url_host(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| "<invalid url>".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn url_host_drops_the_api_key() {
+        assert_eq!(
+            url_host("wss://eth-mainnet.g.alchemy.com/v2/secret-key"),
+            "eth-mainnet.g.alchemy.com"
+        );
+        assert_eq!(
+            url_host("wss://mainnet.example.com:8546/ws?apikey=secret-key"),
+            "mainnet.example.com"
+        );
+        assert_eq!(url_host("not a url secret-key"), "<invalid url>");
+    }
+}
@@ -0,0 +1,853 @@
+use ::common::{trace_id, ChainType, L1Finality, Network, NetworkConfig};
+use ethers::prelude::*;
+use eyre::eyre;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio_postgres::NoTls;
+use tracing::{debug, error, field, info, info_span, warn, Instrument, Span};
+
+use crate::error::{Backoff, IndexerError};
+use crate::fetcher::Fetcher;
+use crate::heads::HeadWatcher;
+use crate::live;
+use crate::logs::log_span;
+use crate::notifier::{Alert, AlertKind, Notifier};
+use crate::shutdown::Shutdown;
+use crate::status::StreamProgress;
+use crate::{arbitrum, opstack, status, telemetry};
+
+/// How long an in-flight batch may keep running after a shutdown signal before it is
+/// rolled back. Kept below the default k8s termination grace period of 30s.
+const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(20);
+/// Seconds between two status checks of the in progress dispute games by default
+const DEFAULT_GAME_REFRESH_PERIOD_SEC: u64 = 600;
+/// The in progress dispute games read per query when refreshing their states
+const GAME_REFRESH_PAGE_SIZE: i64 = 200;
+
+/// The event streams an indexer process can follow
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamKind {
+    /// OutputProposed events of the legacy L2OutputOracle
+    Outputs,
+    /// DisputeGameCreated events of the DisputeGameFactory
+    DisputeGames,
+    /// SendRootUpdated events of the Arbitrum Outbox
+    Arbitrum,
+}
+
+impl StreamKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StreamKind::Outputs => "outputs",
+            StreamKind::DisputeGames => "dispute_games",
+            StreamKind::Arbitrum => "arbitrum",
+        }
+    }
+
+    fn event_signature(&self) -> H256 {
+        match self {
+            StreamKind::Outputs => opstack::OutputProposedFilter::signature(),
+            StreamKind::DisputeGames => opstack::DisputeGameCreatedFilter::signature(),
+            StreamKind::Arbitrum => arbitrum::SendRootUpdatedFilter::signature(),
+        }
+    }
+}
+
+/// Cursor and health of a single stream
+pub struct Stream {
+    pub kind: StreamKind,
+    pub table_name: String,
+    pub address: Address,
+    /// The next L1 block to index
+    pub from_block: U64,
+    /// The last L1 block this stream may index, e.g. the block before the FDG transition
+    pub to_block_cap: Option<u64>,
+    /// The next dispute game index, only used by the DisputeGames stream
+    pub next_game_index: u64,
+    /// The last output index stored, only used by the Outputs stream
+    pub last_output_index: Option<u64>,
+    /// The timestamp of the last L1 block indexed, unknown until the first batch
+    pub processed_l1_timestamp: Option<u64>,
+    /// Set once the stream hit a permanent error
+    pub halted: bool,
+    /// Set while the lag of the stream is above `max_lag_sec`, so that it alerts once
+    pub lag_alerted: bool,
+}
+
+/// Everything the streams of one network share: RPC clients, database and config
+pub struct Indexer {
+    pub network: Network,
+    pub network_config: NetworkConfig,
+    pub rpc_client: Arc<Provider<Http>>,
+    /// L2 RPC used to resolve output data (OP stack) or block hashes (Arbitrum stack)
+    pub l2_fetcher: Option<Fetcher>,
+    pub db_url: String,
+    pub pg_client: tokio_postgres::Client,
+    pub block_delay: U64,
+    pub batch_size: u64,
+    pub notifier: Notifier,
+    /// When the in progress dispute games were last checked
+    pub last_game_refresh: Option<Instant>,
+}
+
+/// A function that connects to postgres and drives the connection in the background.
+/// Parameters:
+/// * db_url: The postgres connection string
+///
+/// Returns:
+/// * The postgres client
+pub async fn connect_db(db_url: &str) -> Result<tokio_postgres::Client, tokio_postgres::Error> {
+    let (pg_client, connection) = tokio_postgres::connect(db_url, NoTls).await?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            error!(error = %e, "PostgreSQL connection error");
+        }
+    });
+    Ok(pg_client)
+}
+
+/// A function that checks that the tables the indexer writes to exist. They are only
+/// created and changed by the SQL files of `migrations/`, which have to be applied first.
+/// Parameters:
+/// * client: The postgres client
+/// * tables: The names of the tables
+///
+/// Returns:
+/// * Nothing, or an error naming the missing tables
+pub async fn check_tables(client: &tokio_postgres::Client, tables: &[&str]) -> eyre::Result<()> {
+    let mut missing = Vec::new();
+    for table in tables {
+        let row = client
+            .query_one("SELECT to_regclass($1::TEXT) IS NOT NULL", &[table])
+            .await?;
+        if !row.get::<_, bool>(0) {
+            missing.push(*table);
+        }
+    }
+    if !missing.is_empty() {
+        return Err(eyre!(
+            "Missing tables {}, apply the SQL files of migrations/ first",
+            missing.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+/// A function that returns the highest L1 block of the events stored in a table, the
+/// stream resumes after it.
+/// Parameters:
+/// * table_name: The name of the postgres table
+/// * client: The postgres client
+///
+/// Returns:
+/// * The highest l1_block_number, or None if the table is empty
+pub async fn get_highest_l1_block_number(
+    table_name: &str,
+    client: &tokio_postgres::Client,
+) -> Result<Option<i64>, tokio_postgres::Error> {
+    let query = format!("SELECT MAX(l1_block_number)::BIGINT FROM {}", table_name);
+    let row = client.query_one(&query, &[]).await?;
+    let max_blocknum: Option<i64> = row.get(0);
+    debug!(table = %table_name, ?max_blocknum, "Highest indexed L1 block");
+    Ok(max_blocknum)
+}
+
+impl Indexer {
+    /// Runs the indexing loop until shutdown is requested or every stream is halted.
+    ///
+    /// Transient errors are retried in place with exponential backoff. Permanent errors
+    /// halt only the affected stream and are persisted to `indexer_status`; the other
+    /// streams keep going. Returns `Ok` on a requested shutdown and an error once no
+    /// stream is left running.
+    pub async fn run(
+        &mut self,
+        mut streams: Vec<Stream>,
+        head_watcher: &mut HeadWatcher,
+        mut shutdown: Shutdown,
+    ) -> eyre::Result<()> {
+        let network = self.network.to_string();
+        while !shutdown.is_requested() {
+            // Get the highest block we are allowed to index, either latest minus block delay or an L1 finality tag
+            let indexable_block = match self.get_l1_head().await {
+                Ok((head_block, head_timestamp)) => {
+                    // Best effort, the status is only reported
+                    if let Err(e) = status::record_l1_head(
+                        &self.pg_client,
+                        &network,
+                        head_block.as_u64(),
+                        head_timestamp,
+                    )
+                    .await
+                    {
+                        warn!(error = %e, "Failed to persist the L1 head");
+                    }
+                    self.get_indexable_block_number(head_block)
+                        .await
+                        .map(|block| (block, head_block.as_u64(), head_timestamp))
+                }
+                Err(err) => Err(err),
+            };
+            match indexable_block {
+                Ok((new_block_num, head_block, head_timestamp)) => {
+                    for stream in streams.iter_mut().filter(|stream| !stream.halted) {
+                        if shutdown.is_requested() {
+                            break;
+                        }
+                        self.run_stream(stream, new_block_num, &mut shutdown).await;
+                    }
+                    for stream in streams.iter_mut() {
+                        let lag_seconds = record_lag(&network, stream, head_block, head_timestamp);
+                        self.check_lag(stream, lag_seconds);
+                    }
+                    if !shutdown.is_requested() {
+                        self.refresh_game_states(&streams).await;
+                    }
+                }
+                Err(err) => warn!(error = %err, "Failed to get the indexable L1 block, retrying"),
+            }
+
+            if streams.iter().all(|stream| stream.halted) {
+                return Err(eyre!(
+                    "All streams of {} are halted, see indexer_status for the errors",
+                    self.network
+                ));
+            }
+
+            // Wait for the next L1 head or poll period
+            tokio::select! {
+                _ = head_watcher.wait() => {}
+                _ = shutdown.requested() => {}
+            }
+        }
+
+        info!(network = %self.network, "Indexer stopped cleanly");
+        Ok(())
+    }
+
+    /// Indexes the next batch of a stream, retrying transient errors until it succeeds
+    /// and halting the stream on a permanent error.
+    ///
+    /// On shutdown the in-flight batch gets `SHUTDOWN_GRACE_PERIOD` to commit; after that
+    /// it is dropped, which rolls its transaction back.
+    async fn run_stream(
+        &mut self,
+        stream: &mut Stream,
+        new_block_num: U64,
+        shutdown: &mut Shutdown,
+    ) {
+        let network = self.network.to_string();
+        let mut backoff = Backoff::default();
+
+        loop {
+            let kind = stream.kind;
+            let result = {
+                let batch = self.index_next_batch(stream, new_block_num);
+                tokio::pin!(batch);
+                tokio::select! {
+                    result = &mut batch => result,
+                    _ = shutdown.requested() => {
+                        info!(network = %network, stream = kind.as_str(), "Finishing in-flight batch before shutdown");
+                        match tokio::time::timeout(SHUTDOWN_GRACE_PERIOD, batch).await {
+                            Ok(result) => result,
+                            Err(_) => {
+                                warn!(network = %network, stream = kind.as_str(), "In-flight batch did not finish in time, rolled back");
+                                return;
+                            }
+                        }
+                    }
+                }
+            };
+            let err = match result {
+                Ok(()) => return,
+                Err(err) => err,
+            };
+
+            if !err.is_transient() {
+                error!(
+                    network = %network,
+                    stream = kind.as_str(),
+                    error = %err,
+                    "Permanent error, halting the stream"
+                );
+                stream.halted = true;
+                if let Err(e) = status::record_stream_error(
+                    &self.pg_client,
+                    &network,
+                    stream.kind,
+                    &err.to_string(),
+                    true,
+                )
+                .await
+                {
+                    error!(network = %network, stream = kind.as_str(), error = %e, "Failed to persist halted state");
+                }
+                return;
+            }
+
+            warn!(
+                network = %network,
+                stream = kind.as_str(),
+                error = %err,
+                retry_in = ?backoff.next_delay(),
+                "Transient error, retrying"
+            );
+            // Best effort, the database may be the thing that is down
+            let _ = status::record_stream_error(
+                &self.pg_client,
+                &network,
+                stream.kind,
+                &err.to_string(),
+                false,
+            )
+            .await;
+            if shutdown.is_requested() {
+                return;
+            }
+            tokio::select! {
+                _ = backoff.wait() => {}
+                _ = shutdown.requested() => return,
+            }
+
+            if self.pg_client.is_closed() {
+                match connect_db(&self.db_url).await {
+                    Ok(pg_client) => {
+                        info!("Reconnected to PostgreSQL");
+                        self.pg_client = pg_client;
+                    }
+                    Err(e) => warn!(error = %e, "Failed to reconnect to PostgreSQL"),
+                }
+            }
+        }
+    }
+
+    /// A function that alerts once when a stream falls behind the L1 head by more than
+    /// `max_lag_sec`, and again only after it caught up.
+    fn check_lag(&self, stream: &mut Stream, lag_seconds: Option<u64>) {
+        let (Some(max_lag_sec), Some(lag_seconds)) =
+            (self.notifier.rules().max_lag_sec, lag_seconds)
+        else {
+            return;
+        };
+        if lag_seconds <= max_lag_sec {
+            if stream.lag_alerted {
+                info!(
+                    stream = stream.kind.as_str(),
+                    lag_seconds, "Stream caught up with the L1 head"
+                );
+            }
+            stream.lag_alerted = false;
+            return;
+        }
+        if stream.lag_alerted {
+            return;
+        }
+        stream.lag_alerted = true;
+        self.notifier.send(
+            self.notifier
+                .alert(
+                    AlertKind::IndexingLag,
+                    format!(
+                        "The {} stream is {}s behind the L1 head",
+                        stream.kind.as_str(),
+                        lag_seconds
+                    ),
+                    json!({
+                        "stream": stream.kind.as_str(),
+                        "lag_seconds": lag_seconds,
+                        "max_lag_sec": max_lag_sec,
+                        "last_processed_l1_block": stream.from_block.as_u64().saturating_sub(1),
+                        "halted": stream.halted,
+                    }),
+                )
+                .into_iter()
+                .collect(),
+        );
+    }
+
+    /// Stores the resolution of the in progress dispute games every
+    /// `game_refresh_period_sec`, alerting on CHALLENGER_WINS. Best effort, a failed
+    /// refresh is retried at the next period.
+    async fn refresh_game_states(&mut self, streams: &[Stream]) {
+        let period = Duration::from_secs(
+            self.network_config
+                .game_refresh_period_sec
+                .unwrap_or(DEFAULT_GAME_REFRESH_PERIOD_SEC),
+        );
+        if self
+            .last_game_refresh
+            .is_some_and(|refreshed| refreshed.elapsed() < period)
+        {
+            return;
+        }
+        let Some(stream) = streams
+            .iter()
+            .find(|stream| stream.kind == StreamKind::DisputeGames && !stream.halted)
+        else {
+            return;
+        };
+        self.last_game_refresh = Some(Instant::now());
+
+        let network = self.network.to_string();
+        let resolutions = match opstack::refresh_game_states(
+            &stream.table_name,
+            &self.pg_client,
+            self.rpc_client.clone(),
+            GAME_REFRESH_PAGE_SIZE,
+        )
+        .await
+        {
+            Ok(resolutions) => resolutions,
+            Err(e) => {
+                warn!(error = %e, "Failed to refresh the dispute game states");
+                return;
+            }
+        };
+        if resolutions.is_empty() {
+            return;
+        }
+
+        let events: Vec<_> = resolutions
+            .iter()
+            .map(|game| live::dispute_game_resolved(&network, game))
+            .collect();
+        if let Err(e) = live::publish(&self.pg_client, &events).await {
+            warn!(error = %e, "Failed to publish the dispute game resolutions");
+        }
+
+        let mut alerts = Vec::new();
+        for game in resolutions.iter() {
+            info!(
+                game_index = game.game_index,
+                game_address = %game.game_address,
+                game_state = game.game_state,
+                "Dispute game resolved"
+            );
+            if game.game_state == opstack::GAME_CHALLENGER_WINS {
+                alerts.extend(self.notifier.alert(
+                    AlertKind::ChallengerWins,
+                    format!("Dispute game {} resolved CHALLENGER_WINS", game.game_index),
+                    json!({
+                        "game_index": game.game_index,
+                        "game_address": game.game_address,
+                    }),
+                ));
+            }
+        }
+        self.notifier.send(alerts);
+        match opstack::count_games_by_state(&stream.table_name, &self.pg_client).await {
+            Ok(games) => telemetry::record_dispute_games(&network, &games),
+            Err(e) => warn!(error = %e, "Failed to count the dispute games"),
+        }
+    }
+
+    /// A function that returns the latest L1 block number and its timestamp.
+    async fn get_l1_head(&self) -> Result<(U64, u64), IndexerError> {
+        telemetry::observe_rpc(
+            "eth_getBlockByNumber",
+            self.rpc_client.get_block(BlockNumber::Latest),
+        )
+        .await?
+        .and_then(|block| Some((block.number?, block.timestamp.as_u64())))
+        .ok_or_else(|| IndexerError::Rpc("L1 node returned no latest block".to_string()))
+    }
+
+    /// A function that returns the timestamp of an L1 block.
+    async fn get_block_timestamp(&self, block_number: u64) -> Result<u64, IndexerError> {
+        telemetry::observe_rpc(
+            "eth_getBlockByNumber",
+            self.rpc_client.get_block(block_number),
+        )
+        .await?
+        .map(|block| block.timestamp.as_u64())
+        .ok_or_else(|| IndexerError::Rpc(format!("L1 node returned no block {block_number}")))
+    }
+
+    /// A function that returns the highest L1 block the indexer may process.
+    /// Parameters:
+    /// * current_latest_block: The latest L1 block
+    async fn get_indexable_block_number(
+        &self,
+        current_latest_block: U64,
+    ) -> Result<U64, IndexerError> {
+        let tag = match self.network_config.l1_finality {
+            L1Finality::Latest => {
+                // Apply block delay to avoid indexing recent blocks
+                return Ok(if current_latest_block > self.block_delay {
+                    current_latest_block - self.block_delay
+                } else {
+                    U64([0]) // Fallback to 0 if block_delay is larger than current block
+                });
+            }
+            L1Finality::Safe => BlockNumber::Safe,
+            L1Finality::Finalized => BlockNumber::Finalized,
+        };
+
+        telemetry::observe_rpc("eth_getBlockByNumber", self.rpc_client.get_block(tag))
+            .await?
+            .and_then(|block| block.number)
+            .ok_or_else(|| IndexerError::Rpc(format!("L1 node returned no {tag} block")))
+    }
+
+    /// Fetches and stores the logs of the next batch of a stream.
+    ///
+    /// All rows of a batch are written in one transaction and the stream cursor only
+    /// moves after the commit, so a failed batch can be retried without duplicates.
+    async fn index_next_batch(
+        &mut self,
+        stream: &mut Stream,
+        new_block_num: U64,
+    ) -> Result<(), IndexerError> {
+        let from_block = stream.from_block.as_u64();
+
+        // Ensure we don't try to index blocks that don't exist
+        if new_block_num.as_u64() < from_block {
+            debug!(
+                network = %self.network,
+                stream = stream.kind.as_str(),
+                indexable_block = new_block_num.as_u64(),
+                from_block,
+                "Waiting for more blocks to be available"
+            );
+            return Ok(());
+        }
+
+        // Compute the upper limit of this batch
+        let block_gap = new_block_num.as_u64().saturating_sub(from_block);
+        let upper_limit = if block_gap > self.batch_size {
+            from_block + self.batch_size - 1
+        } else {
+            new_block_num.as_u64()
+        };
+        let upper_limit = upper_limit.min(stream.to_block_cap.unwrap_or(u64::MAX));
+        if from_block > upper_limit {
+            return Ok(());
+        }
+
+        let span = info_span!(
+            "batch",
+            network = %self.network,
+            stream = stream.kind.as_str(),
+            from_block,
+            to_block = upper_limit,
+            trace_id = field::Empty
+        );
+        span.record("trace_id", trace_id(&span));
+        self.index_range(stream, from_block, upper_limit)
+            .instrument(span)
+            .await
+    }
+
+    /// Fetches and stores the logs of a stream between two L1 blocks, both included.
+    async fn index_range(
+        &mut self,
+        stream: &mut Stream,
+        from_block: u64,
+        upper_limit: u64,
+    ) -> Result<(), IndexerError> {
+        let filter = Filter::new()
+            .topic0(stream.kind.event_signature())
+            .address(stream.address)
+            .from_block(from_block)
+            .to_block(upper_limit);
+        let logs = telemetry::observe_rpc("eth_getLogs", self.rpc_client.get_logs(&filter)).await?;
+
+        let last_event_l1_timestamp = match logs.last().and_then(|log| log.block_number) {
+            Some(block_number) => Some(self.get_block_timestamp(block_number.as_u64()).await?),
+            None => None,
+        };
+        let progress = StreamProgress {
+            l1_block: upper_limit,
+            l1_timestamp: self.get_block_timestamp(upper_limit).await?,
+            last_event_l1_timestamp,
+        };
+        let network = self.network.to_string();
+        telemetry::record_batch_logs(&network, stream.kind.as_str(), logs.len());
+
+        match stream.kind {
+            StreamKind::Outputs => {
+                info!(logs = logs.len(), "Fetched OutputProposed events");
+                let mut params = Vec::with_capacity(logs.len());
+                for log in logs.iter() {
+                    params.push(
+                        opstack::handle_opstack_events(log, self.l2_fetcher.as_ref())
+                            .instrument(log_span(log, None))
+                            .await?,
+                    );
+                }
+
+                let alerts = self.output_alerts(stream.last_output_index, &params);
+                let events: Vec<_> = params
+                    .iter()
+                    .map(|output| live::output_proposed(&network, output))
+                    .collect();
+                let last_output_index = params
+                    .last()
+                    .map(|params| params.l2_output_index.as_u64())
+                    .or(stream.last_output_index);
+
+                let started = Instant::now();
+                let rows = params.len();
+                async {
+                    let transaction = self.pg_client.transaction().await?;
+                    for params in params {
+                        opstack::insert_into_postgres(
+                            stream.table_name.clone(),
+                            &transaction,
+                            params,
+                        )
+                        .await?;
+                    }
+                    live::publish(&transaction, &events).await?;
+                    status::record_stream_progress(&transaction, &network, stream.kind, &progress)
+                        .await?;
+                    Ok::<_, IndexerError>(transaction.commit().await?)
+                }
+                .instrument(db_write_span(&stream.table_name, rows))
+                .await?;
+                telemetry::record_db_write(&network, stream.kind.as_str(), started);
+                stream.last_output_index = last_output_index;
+                self.notifier.send(alerts);
+            }
+            StreamKind::Arbitrum => {
+                info!(logs = logs.len(), "Fetched SendRootUpdated events");
+                let fetcher = self.l2_fetcher.as_ref().ok_or_else(|| {
+                    IndexerError::Config("no L2 RPC URL configured for this network".to_string())
+                })?;
+                let mut params = Vec::with_capacity(logs.len());
+                for log in logs.iter() {
+                    params.push(
+                        arbitrum::handle_arbitrum_events(log, fetcher)
+                            .instrument(log_span(log, None))
+                            .await?,
+                    );
+                }
+
+                let events: Vec<_> = params
+                    .iter()
+                    .map(|output| live::send_root_updated(&network, output))
+                    .collect();
+
+                let started = Instant::now();
+                let rows = params.len();
+                async {
+                    let transaction = self.pg_client.transaction().await?;
+                    for params in params {
+                        arbitrum::insert_into_postgres(
+                            stream.table_name.clone(),
+                            &transaction,
+                            params,
+                        )
+                        .await?;
+                    }
+                    live::publish(&transaction, &events).await?;
+                    status::record_stream_progress(&transaction, &network, stream.kind, &progress)
+                        .await?;
+                    Ok::<_, IndexerError>(transaction.commit().await?)
+                }
+                .instrument(db_write_span(&stream.table_name, rows))
+                .await?;
+                telemetry::record_db_write(&network, stream.kind.as_str(), started);
+            }
+            StreamKind::DisputeGames => {
+                info!(games = logs.len(), "Fetched DisputeGameCreated events");
+                let fetcher = self
+                    .l2_fetcher
+                    .as_ref()
+                    .ok_or_else(|| IndexerError::Config("L2_RPC_URL must be set.".to_string()))?;
+                let mut params = Vec::with_capacity(logs.len());
+                for (offset, log) in logs.iter().enumerate() {
+                    let game_index = stream.next_game_index + offset as u64;
+                    params.push(
+                        opstack::handle_opstack_fdg_events(
+                            log,
+                            &self.network,
+                            &self.network_config,
+                            self.rpc_client.clone(),
+                            fetcher,
+                            game_index,
+                        )
+                        .instrument(log_span(log, Some(game_index)))
+                        .await?,
+                    );
+                }
+
+                let alerts = self.dispute_game_alerts(&params);
+                let events: Vec<_> = params
+                    .iter()
+                    .map(|game| live::dispute_game_created(&network, game))
+                    .collect();
+
+                let started = Instant::now();
+                let rows = params.len();
+                async {
+                    let transaction = self.pg_client.transaction().await?;
+                    for params in params {
+                        opstack::insert_fdg_into_postgres(
+                            stream.table_name.clone(),
+                            &transaction,
+                            params,
+                        )
+                        .await?;
+                    }
+                    live::publish(&transaction, &events).await?;
+                    status::record_stream_progress(&transaction, &network, stream.kind, &progress)
+                        .await?;
+                    Ok::<_, IndexerError>(transaction.commit().await?)
+                }
+                .instrument(db_write_span(&stream.table_name, rows))
+                .await?;
+                telemetry::record_db_write(&network, stream.kind.as_str(), started);
+
+                // Only advance game_index once the whole batch is stored
+                stream.next_game_index += logs.len() as u64;
+                self.notifier.send(alerts);
+                debug!(
+                    next_game_index = stream.next_game_index,
+                    "Stored the dispute games"
+                );
+
+                if !logs.is_empty() {
+                    // Best effort, the games are already stored
+                    match opstack::count_games_by_state(&stream.table_name, &self.pg_client).await {
+                        Ok(games) => telemetry::record_dispute_games(&network, &games),
+                        Err(e) => warn!(error = %e, "Failed to count the dispute games"),
+                    }
+                }
+            }
+        }
+
+        stream.from_block = U64([upper_limit + 1]);
+        stream.processed_l1_timestamp = Some(progress.l1_timestamp);
+        Ok(())
+    }
+
+    /// A function that returns the alerts raised by the outputs of a batch: output
+    /// indexes that skip or go back, and output roots that do not match L2.
+    /// Parameters:
+    /// * last_output_index: The last output index stored before the batch
+    /// * outputs: The outputs of the batch, in L1 order
+    fn output_alerts(
+        &self,
+        mut last_output_index: Option<u64>,
+        outputs: &[opstack::OPStackParameters],
+    ) -> Vec<Alert> {
+        let mut alerts = Vec::new();
+        for output in outputs {
+            let output_index = output.l2_output_index.as_u64();
+            let details = json!({
+                "l2_output_index": output_index,
+                "l2_output_root": format!("{:#x}", output.l2_output_root),
+                "l2_block_number": output.l2_block_number.to_string(),
+                "l1_block_number": output.l1_block_number.as_u64(),
+                "l1_transaction_hash": output.l1_transaction_hash.to_string(),
+            });
+            if let Some(last) = last_output_index.filter(|last| output_index != last + 1) {
+                alerts.extend(self.notifier.alert(
+                    AlertKind::OutputIndexGap,
+                    format!(
+                        "Output index {} was proposed after output index {}",
+                        output_index, last
+                    ),
+                    json!({ "previous_l2_output_index": last, "output": details.clone() }),
+                ));
+            }
+            if output.output_root_verified() == Some(false) {
+                alerts.extend(self.notifier.alert(
+                    AlertKind::RootClaimMismatch,
+                    format!(
+                        "Output root {} does not match L2 block {}",
+                        output_index, output.l2_block_number
+                    ),
+                    details,
+                ));
+            }
+            last_output_index = Some(output_index);
+        }
+        alerts
+    }
+
+    /// A function that returns the alerts raised by the dispute games of a batch: games
+    /// created by an untrusted proposer, and root claims that do not match L2.
+    /// Parameters:
+    /// * games: The dispute games of the batch
+    fn dispute_game_alerts(&self, games: &[opstack::OPStackDisputeGameParameters]) -> Vec<Alert> {
+        // Sepolia games do not expose their creator, so it is only known on mainnet
+        let trusted_proposer = self
+            .network_config
+            .trusted_proposer_address
+            .as_deref()
+            .and_then(|address| address.parse::<Address>().ok())
+            .filter(|_| self.network.chain_type == ChainType::Mainnet);
+
+        let mut alerts = Vec::new();
+        for game in games {
+            let details = json!({
+                "game_index": game.game_index,
+                "game_address": format!("{:#x}", game.game_address),
+                "proposer_address": format!("{:#x}", game.proposer_address),
+                "root_claim": format!("{:#x}", game.root_claim),
+                "l2_block_number": game.l2_block_number.to_string(),
+                "l1_block_number": game.l1_block_number.as_u64(),
+                "l1_transaction_hash": game.l1_transaction_hash.to_string(),
+            });
+            if trusted_proposer.is_some_and(|trusted| trusted != game.proposer_address) {
+                alerts.extend(self.notifier.alert(
+                    AlertKind::UntrustedProposer,
+                    format!(
+                        "Dispute game {} was created by untrusted proposer {:#x}",
+                        game.game_index, game.proposer_address
+                    ),
+                    details.clone(),
+                ));
+            }
+            if game.claim_verified == Some(false) {
+                alerts.extend(self.notifier.alert(
+                    AlertKind::RootClaimMismatch,
+                    format!(
+                        "Root claim of dispute game {} does not match L2 block {}",
+                        game.game_index, game.l2_block_number
+                    ),
+                    details,
+                ));
+            }
+        }
+        alerts
+    }
+}
+
+/// The span of the transaction storing the rows of a batch
+fn db_write_span(table_name: &str, rows: usize) -> Span {
+    info_span!(
+        "db.write",
+        db.system = "postgresql",
+        db.collection.name = table_name,
+        rows
+    )
+}
+
+/// A function that publishes how far a stream is behind the L1 head.
+/// A stream that reached its block cap has nothing left to index and no lag.
+///
+/// Returns:
+/// * The lag in seconds, unknown until the stream indexed its first batch
+fn record_lag(network: &str, stream: &Stream, head_block: u64, head_timestamp: u64) -> Option<u64> {
+    let processed_block = stream.from_block.as_u64().saturating_sub(1);
+    if stream
+        .to_block_cap
+        .is_some_and(|cap| processed_block >= cap)
+    {
+        telemetry::record_lag(network, stream.kind.as_str(), 0, Some(0));
+        return Some(0);
+    }
+    let lag_seconds = stream
+        .processed_l1_timestamp
+        .map(|timestamp| head_timestamp.saturating_sub(timestamp));
+    telemetry::record_lag(
+        network,
+        stream.kind.as_str(),
+        head_block.saturating_sub(processed_block),
+        lag_seconds,
+    );
+    lag_seconds
+}
@@ -0,0 +1,103 @@
+use common::{LiveEvent, LiveEventType, LIVE_EVENTS_CHANNEL};
+use serde_json::json;
+use tokio_postgres::GenericClient;
+
+use crate::arbitrum::ArbitrumParameters;
+use crate::error::IndexerError;
+use crate::opstack::{GameResolution, OPStackDisputeGameParameters, OPStackParameters};
+
+/// A function that builds the event of an output proposed to the L2OutputOracle.
+pub fn output_proposed(network: &str, output: &OPStackParameters) -> LiveEvent {
+    let l2_block_number: u64 = output.l2_block_number.try_into().unwrap_or(0u64);
+    LiveEvent::new(
+        network,
+        LiveEventType::OutputProposed,
+        l2_block_number,
+        json!({
+            "l2_output_root": output.l2_output_root.to_string(),
+            "l2_output_index": output.l2_output_index.as_u64(),
+            "l2_block_number": l2_block_number,
+            "l1_block_number": output.l1_block_number.as_u64(),
+            "l1_transaction_hash": output.l1_transaction_hash.to_string(),
+        }),
+    )
+}
+
+/// A function that builds the event of a send root updated on the Arbitrum rollup.
+pub fn send_root_updated(network: &str, output: &ArbitrumParameters) -> LiveEvent {
+    let l2_block_number = output.l2_block_number.as_u64();
+    LiveEvent::new(
+        network,
+        LiveEventType::OutputProposed,
+        l2_block_number,
+        json!({
+            "l2_output_root": output.l2_output_root.to_string(),
+            "l2_block_number": l2_block_number,
+            "l1_block_number": output.l1_block_number.as_u64(),
+            "l1_transaction_hash": output.l1_transaction_hash.to_string(),
+        }),
+    )
+}
+
+/// A function that builds the event of a dispute game created by the factory.
+pub fn dispute_game_created(network: &str, game: &OPStackDisputeGameParameters) -> LiveEvent {
+    LiveEvent::new(
+        network,
+        LiveEventType::DisputeGameCreated,
+        game.game_index,
+        json!({
+            "game_index": game.game_index,
+            "game_address": format!("{:#x}", game.game_address),
+            "game_type": game.game_type,
+            "game_state": game.game_state,
+            "root_claim": format!("{:#x}", game.root_claim),
+            "proposer_address": format!("{:#x}", game.proposer_address),
+            "l2_block_number": game.l2_block_number_safe,
+            "claim_verified": game.claim_verified,
+            "l1_block_number": game.l1_block_number.as_u64(),
+            "l1_transaction_hash": game.l1_transaction_hash.to_string(),
+        }),
+    )
+}
+
+/// A function that builds the event of an in progress dispute game that resolved.
+pub fn dispute_game_resolved(network: &str, game: &GameResolution) -> LiveEvent {
+    LiveEvent::new(
+        network,
+        LiveEventType::DisputeGameResolved,
+        game.game_index,
+        json!({
+            "game_index": game.game_index,
+            "game_address": game.game_address,
+            "l2_block_number": game.l2_block_number,
+            "game_state": game.game_state,
+        }),
+    )
+}
+
+/// A function that publishes events with `pg_notify`. Within a transaction they are only
+/// delivered to the listeners once it commits, and dropped if it rolls back.
+/// Parameters:
+/// * client: The postgres client or transaction storing the rows of the events
+/// * events: The events to publish, in order
+///
+/// Returns:
+/// Returns nothing except for error
+pub async fn publish(
+    client: &impl GenericClient,
+    events: &[LiveEvent],
+) -> Result<(), IndexerError> {
+    for event in events {
+        // Events are a few hundred bytes, well below the 8000 bytes limit of a payload
+        let payload = serde_json::to_string(event).map_err(|e| {
+            IndexerError::Invariant(format!("failed to serialize the event {}: {}", event.id, e))
+        })?;
+        client
+            .execute(
+                "SELECT pg_notify($1, $2)",
+                &[&LIVE_EVENTS_CHANNEL, &payload],
+            )
+            .await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use crate::error::IndexerError;
+use ethers::abi::RawLog;
+use ethers::prelude::*;
+use tracing::{info_span, Span};
+
+fn missing(field: &str) -> IndexerError {
+    IndexerError::Decode(format!("log is missing {field}"))
+}
+
+/// The L1 coordinates stored next to every indexed event
+pub struct L1LogMeta {
+    pub transaction_hash: Bytes,
+    pub block_number: U64,
+    pub transaction_index: U64,
+    pub block_hash: Bytes,
+}
+
+impl L1LogMeta {
+    /// Extracts the L1 coordinates of a mined log, failing if the node returned a pending log
+    pub fn from_log(log: &Log) -> Result<Self, IndexerError> {
+        let transaction_hash = log
+            .transaction_hash
+            .ok_or_else(|| missing("transaction_hash"))?;
+        let block_hash = log.block_hash.ok_or_else(|| missing("block_hash"))?;
+
+        Ok(Self {
+            transaction_hash: Bytes::from(transaction_hash.as_bytes().to_vec()),
+            block_number: log.block_number.ok_or_else(|| missing("block_number"))?,
+            transaction_index: log
+                .transaction_index
+                .ok_or_else(|| missing("transaction_index"))?,
+            block_hash: Bytes::from(block_hash.as_bytes().to_vec()),
+        })
+    }
+}
+
+/// Decodes a raw log into the typed event `E` generated by abigen.
+/// Parameters:
+/// * log: The raw log returned by `eth_getLogs`
+///
+/// Returns:
+/// * The decoded event, or an error naming the event and transaction if the log is malformed
+pub fn decode_log<E: EthEvent>(log: &Log) -> Result<E, IndexerError> {
+    E::decode_log(&RawLog::from(log.clone())).map_err(|e| {
+        IndexerError::Decode(format!(
+            "failed to decode {} log (tx {:?}, log index {:?}): {e}",
+            E::name(),
+            log.transaction_hash,
+            log.log_index
+        ))
+    })
+}
+
+/// A function that opens the span under which a log is handled, so that every line
+/// logged while processing it carries its L1 coordinates.
+/// Parameters:
+/// * log: The raw log returned by `eth_getLogs`
+/// * game_index: The index of the dispute game the log created, if any
+pub fn log_span(log: &Log, game_index: Option<u64>) -> Span {
+    info_span!(
+        "log",
+        l1_block = log.block_number.map(|block| block.as_u64()),
+        l1_transaction_hash = log.transaction_hash.map(|hash| format!("{hash:#x}")),
+        log_index = log.log_index.map(|index| index.as_u64()),
+        game_index
+    )
+}
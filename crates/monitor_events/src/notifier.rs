@@ -0,0 +1,227 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ::common::{AlertRules, Network};
+use eyre::{eyre, Result, WrapErr};
+use serde::Serialize;
+use serde_json::Value;
+use tracing::{info, warn};
+
+/// How long a webhook may take to accept an alert
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The anomalies an alert can be raised for, each enabled by a rule of [`AlertRules`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    OutputIndexGap,
+    UntrustedProposer,
+    ChallengerWins,
+    RootClaimMismatch,
+    IndexingLag,
+}
+
+impl AlertKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertKind::OutputIndexGap => "output_index_gap",
+            AlertKind::UntrustedProposer => "untrusted_proposer",
+            AlertKind::ChallengerWins => "challenger_wins",
+            AlertKind::RootClaimMismatch => "root_claim_mismatch",
+            AlertKind::IndexingLag => "indexing_lag",
+        }
+    }
+}
+
+/// An anomaly found while indexing, as delivered to the sinks
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub kind: AlertKind,
+    pub network: String,
+    pub message: String,
+    /// The fields identifying the event or stream, e.g. game_index and game_address
+    pub details: Value,
+    /// Unix timestamp at which the alert was raised
+    pub raised_at: u64,
+}
+
+/// Where alerts are delivered
+enum Sink {
+    /// One JSON object per line on stdout
+    Stdout,
+    /// One JSON object per line appended to a file
+    File(String),
+    /// One POST with the JSON alert per alert
+    Webhook {
+        url: String,
+        client: reqwest::Client,
+    },
+}
+
+impl Sink {
+    /// A function that parses a sink of `ALERT_SINKS`, e.g. `stdout`,
+    /// `file:/var/log/alerts.jsonl` or `webhook:https://example.com/hook`
+    fn parse(sink: &str) -> Result<Self> {
+        if sink == "stdout" {
+            return Ok(Sink::Stdout);
+        }
+        match sink.split_once(':') {
+            Some(("file", path)) if !path.is_empty() => Ok(Sink::File(path.to_string())),
+            Some(("webhook", url)) if !url.is_empty() => Ok(Sink::Webhook {
+                url: url.to_string(),
+                client: reqwest::Client::builder()
+                    .timeout(WEBHOOK_TIMEOUT)
+                    .build()
+                    .wrap_err("Failed to build the webhook client")?,
+            }),
+            _ => Err(eyre!(
+                "Invalid alert sink {}, expected stdout, file:<path> or webhook:<url>",
+                sink
+            )),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Sink::Stdout => "stdout",
+            Sink::File(_) => "file",
+            Sink::Webhook { .. } => "webhook",
+        }
+    }
+
+    async fn deliver(&self, alert: &Alert) -> Result<()> {
+        match self {
+            Sink::Stdout => {
+                println!("{}", serde_json::to_string(alert)?);
+            }
+            Sink::File(path) => {
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .wrap_err_with(|| format!("Failed to open {}", path))?;
+                serde_json::to_writer(&mut file, alert)?;
+                file.write_all(b"\n")?;
+            }
+            Sink::Webhook { url, client } => {
+                client
+                    .post(url)
+                    .json(alert)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Raises the alerts enabled by the rules of a network and delivers them to the sinks of
+/// `ALERT_SINKS`. Every alert is also logged, with or without sinks.
+pub struct Notifier {
+    network: String,
+    rules: AlertRules,
+    sinks: Arc<Vec<Sink>>,
+}
+
+impl Notifier {
+    /// A function that builds the notifier of a network from `ALERT_SINKS`, a comma
+    /// separated list of sinks.
+    /// Parameters:
+    /// * network: The indexed network
+    /// * rules: The alert rules of the network config
+    ///
+    /// Returns:
+    /// * The notifier, or an error if a sink is invalid
+    pub fn from_env(network: &Network, rules: AlertRules) -> Result<Self> {
+        let sinks = std::env::var("ALERT_SINKS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|sink| !sink.is_empty())
+            .map(Sink::parse)
+            .collect::<Result<Vec<_>>>()?;
+        info!(
+            sinks = ?sinks.iter().map(Sink::name).collect::<Vec<_>>(),
+            ?rules,
+            "Alerting configured"
+        );
+
+        Ok(Self {
+            network: network.to_string(),
+            rules,
+            sinks: Arc::new(sinks),
+        })
+    }
+
+    pub fn rules(&self) -> &AlertRules {
+        &self.rules
+    }
+
+    /// A function that builds an alert if its rule is enabled.
+    /// Parameters:
+    /// * kind: The anomaly
+    /// * message: A human readable description
+    /// * details: The fields identifying the event or stream
+    ///
+    /// Returns:
+    /// * The alert, or None if the network does not alert on this kind
+    pub fn alert(&self, kind: AlertKind, message: String, details: Value) -> Option<Alert> {
+        let enabled = match kind {
+            AlertKind::OutputIndexGap => self.rules.output_index_gap,
+            AlertKind::UntrustedProposer => self.rules.untrusted_proposer,
+            AlertKind::ChallengerWins => self.rules.challenger_wins,
+            AlertKind::RootClaimMismatch => self.rules.root_claim_mismatch,
+            AlertKind::IndexingLag => self.rules.max_lag_sec.is_some(),
+        };
+        enabled.then(|| Alert {
+            kind,
+            network: self.network.clone(),
+            message,
+            details,
+            raised_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        })
+    }
+
+    /// A function that logs alerts and delivers them in the background. Delivery is best
+    /// effort: a failing sink is logged and does not hold indexing back.
+    /// Parameters:
+    /// * alerts: The alerts raised by a batch, once it is stored
+    pub fn send(&self, alerts: Vec<Alert>) {
+        if alerts.is_empty() {
+            return;
+        }
+        for alert in alerts.iter() {
+            warn!(
+                alert = alert.kind.as_str(),
+                details = %alert.details,
+                "{}",
+                alert.message
+            );
+        }
+        if self.sinks.is_empty() {
+            return;
+        }
+
+        let sinks = self.sinks.clone();
+        tokio::spawn(async move {
+            for alert in alerts.iter() {
+                for sink in sinks.iter() {
+                    if let Err(e) = sink.deliver(alert).await {
+                        warn!(
+                            sink = sink.name(),
+                            alert = alert.kind.as_str(),
+                            error = %e,
+                            "Failed to deliver the alert"
+                        );
+                    }
+                }
+            }
+        });
+    }
+}
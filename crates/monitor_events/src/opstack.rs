@@ -1,167 +1,60 @@
-use common::{get_network_config, ChainName, ChainType, Network};
+use common::{ChainName, ChainType, Network, NetworkConfig, OutputRootPreimage};
 
+use crate::error::IndexerError;
 use crate::fetcher::Fetcher;
+use crate::logs::{decode_log, L1LogMeta};
+use crate::shutdown::Shutdown;
+use crate::telemetry;
 use ethers::prelude::*;
+use tokio_postgres::GenericClient;
+use tracing::{debug, info, warn};
 
 abigen!(DisputeGame, "abi/DisputeGame.json");
-use eyre::{eyre, Result};
+abigen!(L2OutputOracle, "abi/L2OutputOracle.json");
+abigen!(DisputeGameFactory, "abi/DisputeGameFactory.json");
 use std::str::FromStr;
 use std::{convert::TryInto, sync::Arc};
 
-fn parse_bytes(name: &str, s: &str) -> eyre::Result<Bytes> {
-    Bytes::from_str(s).map_err(|_| eyre!("invalid {name} hex: {s}"))
+/// The `status()` of a dispute game that is not resolved yet
+pub const GAME_IN_PROGRESS: u64 = 0;
+/// The `status()` of a dispute game whose root claim was proven wrong
+pub const GAME_CHALLENGER_WINS: u64 = 1;
+
+fn parse_bytes(name: &str, s: &str) -> Result<Bytes, IndexerError> {
+    Bytes::from_str(s).map_err(|_| IndexerError::Decode(format!("invalid {name} hex: {s}")))
 }
 
 pub struct OPStackParameters {
-    l2_output_root: Bytes,
-    l2_output_index: U256,
-    l2_block_number: U256,
+    pub(crate) l2_output_root: Bytes,
+    pub(crate) l2_output_index: U256,
+    pub(crate) l2_block_number: U256,
     l1_timestamp: U256,
-    l1_transaction_hash: Bytes,
-    l1_block_number: U64,
+    pub(crate) l1_transaction_hash: Bytes,
+    pub(crate) l1_block_number: U64,
     l1_transaction_index: U64,
     l1_block_hash: Bytes,
+    l2_output_data: Option<L2OutputData>,
 }
 
 pub struct OPStackDisputeGameParameters {
-    game_index: u64,
-    game_address: Address,
-    game_type: u32,
+    pub(crate) game_index: u64,
+    pub(crate) game_address: Address,
+    pub(crate) game_type: u32,
     timestamp: u64,
-    root_claim: Bytes,
-    game_state: u64,
-    proposer_address: Address,
-    l2_block_number: U256,             // Keep original for reference
-    l2_block_number_safe: Option<u64>, // Safe u64 version for database
-    l2_state_root: Option<Bytes>,
-    l2_withdrawal_storage_root: Option<Bytes>,
-    l2_block_hash: Option<Bytes>,
+    pub(crate) root_claim: Bytes,
+    pub(crate) game_state: u64,
+    pub(crate) proposer_address: Address,
+    pub(crate) l2_block_number: U256, // Keep original for reference
+    pub(crate) l2_block_number_safe: Option<u64>, // Safe u64 version for database
+    l2_output_data: Option<L2OutputData>,
+    pub(crate) claim_verified: Option<bool>,
     l1_timestamp: U64,
-    l1_transaction_hash: Bytes,
-    l1_block_number: U64,
+    pub(crate) l1_transaction_hash: Bytes,
+    pub(crate) l1_block_number: U64,
     l1_transaction_index: U64,
     l1_block_hash: Bytes,
 }
 
-/// A function that creates a table if it doesn't exist, and returns the max block number in the table if it exists.
-/// Parameters:
-/// * table_name: The name of the postgres table
-/// * client: The postgres client
-/// Returns:
-/// * Option<i32>: The max block number in the table if it exists, otherwise None
-pub async fn create_opstack_table_if_not_exists(
-    table_name: String,
-    client: &tokio_postgres::Client,
-) -> Result<Option<i32>, tokio_postgres::Error> {
-    let create_table_query = format!("SELECT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_name = '{}') AS table_existence;", table_name);
-    let rows = client.query(&create_table_query, &[]).await?;
-
-    // And then check that we got back the same string we sent over.
-    let exist: bool = rows[0].get(0);
-    println!("Table exist : {exist}");
-    if exist {
-        // Query the maximum l1_block_number
-        let create_table_query = format!(
-            "SELECT MAX(l1_block_number) as MaxBlock from {}",
-            table_name
-        );
-        let rows = client.query(&create_table_query, &[]).await?;
-
-        // Handle possible NULL result for max l1_block_number
-        let max_blocknum: Option<i32> = rows[0].try_get(0)?;
-
-        if let Some(max_num) = max_blocknum {
-            println!("max_blocknum: {max_num}");
-            Ok(Some(max_num))
-        } else {
-            println!("No entries in the table, hence no maximum block number.");
-            Ok(None)
-        }
-    } else {
-        let create_table_query = format!(
-            "CREATE TABLE IF NOT EXISTS {} (
-                id                      SERIAL PRIMARY KEY,
-                l2_output_root          VARCHAR NOT NULL,
-                l2_output_index         INTEGER NOT NULL,
-                l2_block_number         INTEGER NOT NULL,
-                l1_timestamp            INTEGER NOT NULL,
-                l1_transaction_hash     VARCHAR NOT NULL,
-                l1_block_number         INTEGER NOT NULL,
-                l1_transaction_index    INTEGER NOT NULL,
-                l1_block_hash           VARCHAR NOT NULL
-            )",
-            table_name
-        );
-        client.execute(&create_table_query, &[]).await?;
-
-        Ok(None)
-    }
-}
-
-/// A function that creates a table if it doesn't exist, and returns the max block number in the table if it exists.
-/// Parameters:
-/// * table_name: The name of the postgres table
-/// * client: The postgres client
-/// Returns:
-/// * Option<i32>: The max block number in the table if it exists, otherwise None
-pub async fn create_opstack_dispute_games_table_if_not_exists(
-    table_name: String,
-    client: &tokio_postgres::Client,
-) -> Result<Option<i64>, tokio_postgres::Error> {
-    let create_table_query = format!("SELECT EXISTS (SELECT 1 FROM information_schema.tables WHERE table_name = '{}') AS table_existence;", table_name);
-    let rows = client.query(&create_table_query, &[]).await?;
-
-    // And then check that we got back the same string we sent over.
-    let exist: bool = rows[0].get(0);
-    println!("Table exist : {exist}");
-    if exist {
-        // Query the maximum l1_block_number
-        let create_table_query = format!(
-            "SELECT MAX(l1_block_number) as MaxBlock from {}",
-            table_name
-        );
-        let rows = client.query(&create_table_query, &[]).await?;
-
-        // Handle possible NULL result for max l1_block_number
-        let max_blocknum: Option<i64> = rows[0].try_get(0)?;
-
-        if let Some(max_num) = max_blocknum {
-            println!("max_blocknum: {max_num}");
-            Ok(Some(max_num))
-        } else {
-            println!("No entries in the table, hence no maximum block number.");
-            Ok(None)
-        }
-    } else {
-        // l2_state_root, l2_withdrawal_storage_root, l2_block_hash can be null if there is worng game created for nonexistent L2 block
-        let create_table_query = format!(
-            "CREATE TABLE IF NOT EXISTS {} (
-                id                      SERIAL PRIMARY KEY,
-                game_index          BIGINT NOT NULL,
-                game_address          VARCHAR NOT NULL,
-                game_type         BIGINT NOT NULL,
-                timestamp         BIGINT NOT NULL,
-                root_claim            VARCHAR NOT NULL,
-                game_state         BIGINT NOT NULL,
-                proposer_address            VARCHAR NOT NULL,
-                l2_block_number     BIGINT NOT NULL,
-                l2_state_root     VARCHAR,
-                l2_withdrawal_storage_root     VARCHAR,
-                l2_block_hash     VARCHAR,
-                l1_timestamp            BIGINT NOT NULL,
-                l1_transaction_hash     VARCHAR NOT NULL,
-                l1_block_number         BIGINT NOT NULL,
-                l1_transaction_index    BIGINT NOT NULL,
-                l1_block_hash           VARCHAR NOT NULL
-            )",
-            table_name
-        );
-        client.execute(&create_table_query, &[]).await?;
-
-        Ok(None)
-    }
-}
-
 /// A function that inserts data into the postgres table
 /// Parameters:
 /// * table_name: The name of the postgres table
@@ -174,14 +67,17 @@ pub async fn create_opstack_dispute_games_table_if_not_exists(
 /// * l1_block_number: The block number of the l1
 /// * l1_transaction_index: The transaction index of the l1
 /// * l1_block_hash: The block hash of the l1
+/// * l2_output_data: The output root preimage, if the L2 RPC provided it
+///
 /// Returns:
 /// Returns nothing except for error
 pub async fn insert_into_postgres(
     table_name: String,
-    client: &tokio_postgres::Client,
+    client: &impl GenericClient,
     params: OPStackParameters,
 ) -> Result<(), tokio_postgres::Error> {
-    let insert_query = format!("INSERT INTO {} (l2_output_root, l2_output_index, l2_block_number, l1_timestamp, l1_transaction_hash, l1_block_number, l1_transaction_index, l1_block_hash) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)", table_name);
+    let insert_query = format!("INSERT INTO {} (l2_output_root, l2_output_index, l2_block_number, l1_timestamp, l1_transaction_hash, l1_block_number, l1_transaction_index, l1_block_hash, l2_state_root, l2_withdrawal_storage_root, l2_block_hash) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)", table_name);
+    let preimage = params.l2_output_data.as_ref().map(L2OutputData::preimage);
     client
         .execute(
             &insert_query,
@@ -194,6 +90,9 @@ pub async fn insert_into_postgres(
                 &(params.l1_block_number.as_u64() as i32),
                 &(params.l1_transaction_index.as_u64() as i32),
                 &params.l1_block_hash.to_string(),
+                &preimage.as_ref().map(|p| &p.state_root),
+                &preimage.as_ref().map(|p| &p.message_passer_storage_root),
+                &preimage.as_ref().map(|p| &p.block_hash),
             ],
         )
         .await?;
@@ -203,7 +102,7 @@ pub async fn insert_into_postgres(
 
 pub async fn insert_fdg_into_postgres(
     table_name: String,
-    client: &tokio_postgres::Client,
+    client: &impl GenericClient,
     params: OPStackDisputeGameParameters,
 ) -> Result<(), tokio_postgres::Error> {
     let insert_query = format!(
@@ -219,6 +118,7 @@ pub async fn insert_fdg_into_postgres(
             l2_state_root,
             l2_withdrawal_storage_root,
             l2_block_hash,
+            claim_verified,
             l1_timestamp,
             l1_transaction_hash,
             l1_block_number,
@@ -227,7 +127,8 @@ pub async fn insert_fdg_into_postgres(
         ) VALUES (
             $1, $2, $3, $4, $5,
             $6, $7, $8, $9, $10,
-            $11, $12, $13, $14, $15, $16
+            $11, $12, $13, $14, $15,
+            $16, $17
         )",
         table_name
     );
@@ -247,20 +148,18 @@ pub async fn insert_fdg_into_postgres(
 
     // Log if we're using a fallback value
     if params.l2_block_number_safe.is_none() {
-        eprintln!(
-            "Using fallback L2 block number 0 for dispute game {} (original: {})",
-            params.game_index, params.l2_block_number
+        warn!(
+            game_index = params.game_index,
+            l2_block_number = %params.l2_block_number,
+            "Using fallback L2 block number 0 for dispute game"
         );
     }
 
-    let l2_state_root_hex_str: Option<String> =
-        params.l2_state_root.as_ref().map(|h| format!("{:#x}", h));
-    let l2_withdrawal_storage_root_hex_str: Option<String> = params
-        .l2_withdrawal_storage_root
-        .as_ref()
-        .map(|h| format!("{:#x}", h));
-    let l2_block_hash_hex_str: Option<String> =
-        params.l2_block_hash.as_ref().map(|h| format!("{:#x}", h));
+    let preimage = params.l2_output_data.as_ref().map(L2OutputData::preimage);
+    let l2_state_root_hex_str: Option<&String> = preimage.as_ref().map(|p| &p.state_root);
+    let l2_withdrawal_storage_root_hex_str: Option<&String> =
+        preimage.as_ref().map(|p| &p.message_passer_storage_root);
+    let l2_block_hash_hex_str: Option<&String> = preimage.as_ref().map(|p| &p.block_hash);
 
     // let l2_state_root_str = format!("{:#x}", params.l2_state_root);
     // let l2_withdrawal_storage_root_str = format!("{:#x}", params.l2_withdrawal_storage_root);
@@ -287,6 +186,7 @@ pub async fn insert_fdg_into_postgres(
                 &l2_state_root_hex_str,
                 &l2_withdrawal_storage_root_hex_str,
                 &l2_block_hash_hex_str,
+                &params.claim_verified,
                 &l1_timestamp_i64,
                 &l1_tx_hash_str,
                 &l1_block_number_i64,
@@ -299,6 +199,25 @@ pub async fn insert_fdg_into_postgres(
     Ok(())
 }
 
+/// A function that counts the indexed dispute games per game state.
+/// Parameters:
+/// * table_name: The name of the postgres table
+/// * client: The postgres client
+///
+/// Returns:
+/// * The (game_state, number of games) pairs
+pub async fn count_games_by_state(
+    table_name: &str,
+    client: &tokio_postgres::Client,
+) -> Result<Vec<(i64, i64)>, tokio_postgres::Error> {
+    let query = format!(
+        "SELECT game_state, COUNT(*) FROM {} GROUP BY game_state",
+        table_name
+    );
+    let rows = client.query(&query, &[]).await?;
+    Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+}
+
 pub async fn get_highest_game_index(
     table_name: &str,
     client: &tokio_postgres::Client,
@@ -306,7 +225,7 @@ pub async fn get_highest_game_index(
     let query = format!("SELECT MAX(game_index) FROM {}", table_name);
     let rows = client.query(&query, &[]).await?;
 
-    if let Some(row) = rows.get(0) {
+    if let Some(row) = rows.first() {
         let max_index: Option<i64> = row.get(0);
         Ok(max_index.unwrap_or(0).max(0) as u64)
     } else {
@@ -314,21 +233,412 @@ pub async fn get_highest_game_index(
     }
 }
 
-pub fn handle_opstack_events(log: &Log) -> OPStackParameters {
-    let l2_output_root = Bytes::from(log.topics[1].as_bytes().to_vec());
-    let l2_output_index = U256::from_big_endian(log.topics[2].as_bytes());
-    let l2_block_number = U256::from_big_endian(log.topics[3].as_bytes());
-    let l1_timestamp = U256::from_big_endian(&log.data[..]);
-    let l1_transaction_hash = Bytes::from(log.transaction_hash.unwrap().as_bytes().to_vec());
-    let l1_block_number = log.block_number.unwrap();
-    let l1_transaction_index = log.transaction_index.unwrap();
-    let l1_block_hash = Bytes::from(log.block_hash.unwrap().as_bytes().to_vec());
-
-    println!(
-        "output_root = {l2_output_root}, l2OutputIndex = {l2_output_index}, l2BlockNumber = {l2_block_number}, l1Blocknumber = {l1_block_number}, l1Timestamp = {l1_timestamp}, l1_transaction_hash={l1_transaction_hash}, l1_transaction_index={l1_transaction_index}, L1_block_hash={l1_block_hash}"
+impl OPStackParameters {
+    /// Whether the output root matches the preimage fetched from L2, None without it
+    pub fn output_root_verified(&self) -> Option<bool> {
+        self.l2_output_data.as_ref().map(|data| {
+            data.preimage()
+                .verify(&format!("{:#x}", self.l2_output_root))
+        })
+    }
+}
+
+/// A function that returns the highest output index indexed by the legacy outputs stream.
+/// Parameters:
+/// * table_name: The name of the postgres table
+/// * client: The postgres client
+///
+/// Returns:
+/// * The highest l2_output_index, or None if the table is empty
+pub async fn get_highest_output_index(
+    table_name: &str,
+    client: &tokio_postgres::Client,
+) -> Result<Option<u64>, tokio_postgres::Error> {
+    let query = format!("SELECT MAX(l2_output_index) FROM {}", table_name);
+    let row = client.query_one(&query, &[]).await?;
+    let max_index: Option<i32> = row.get(0);
+    Ok(max_index.map(|index| index.max(0) as u64))
+}
+
+/// A dispute game whose status changed since it was indexed
+pub struct GameResolution {
+    pub game_index: u64,
+    pub game_address: String,
+    pub l2_block_number: i64,
+    pub game_state: u64,
+}
+
+/// A function that fetches the status of the in progress dispute games and stores the
+/// games that resolved. Every in progress game is checked, oldest first, reading them
+/// `page_size` at a time.
+/// Parameters:
+/// * table_name: The name of the postgres table
+/// * client: The postgres client
+/// * l1_provider: The L1 provider
+/// * page_size: The number of games read from the table per query
+///
+/// Returns:
+/// * The games that resolved
+pub async fn refresh_game_states(
+    table_name: &str,
+    client: &tokio_postgres::Client,
+    l1_provider: Arc<Provider<Http>>,
+    page_size: i64,
+) -> Result<Vec<GameResolution>, IndexerError> {
+    let select_query = format!(
+        "SELECT game_index, game_address, l2_block_number FROM {}
+            WHERE game_state = {} AND game_index > $1
+            ORDER BY game_index ASC
+            LIMIT $2",
+        table_name, GAME_IN_PROGRESS
+    );
+    let update_query = format!(
+        "UPDATE {} SET game_state = $2 WHERE game_index = $1",
+        table_name
     );
 
-    OPStackParameters {
+    let mut resolutions = Vec::new();
+    let mut after_game_index = -1i64;
+    loop {
+        let rows = client
+            .query(&select_query, &[&after_game_index, &page_size])
+            .await?;
+        for row in rows.iter() {
+            let game_index: i64 = row.get(0);
+            let game_address: String = row.get(1);
+            let l2_block_number: i64 = row.get(2);
+            after_game_index = game_index;
+            let Ok(address) = game_address.parse::<Address>() else {
+                warn!(game_index, %game_address, "Invalid game address, skipping its status");
+                continue;
+            };
+            let dispute_game = DisputeGame::new(address, l1_provider.clone());
+            let game_state = match telemetry::observe_rpc("eth_call", dispute_game.status().call())
+                .await
+                .map_err(|e| IndexerError::from_contract_call("status()", e))
+            {
+                Ok(status) => status as u64,
+                // A game whose status reverts must not keep the others from being checked
+                Err(e) if !e.is_transient() => {
+                    warn!(game_index, error = %e, "Failed to fetch the game status, skipping it");
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            if game_state == GAME_IN_PROGRESS {
+                continue;
+            }
+
+            client
+                .execute(&update_query, &[&game_index, &(game_state as i64)])
+                .await?;
+            resolutions.push(GameResolution {
+                game_index: game_index as u64,
+                game_address,
+                l2_block_number,
+                game_state,
+            });
+        }
+        if (rows.len() as i64) < page_size {
+            return Ok(resolutions);
+        }
+    }
+}
+
+/// The L2 data an output root is computed from, as returned by `optimism_outputAtBlock`
+pub struct L2OutputData {
+    state_root: Bytes,
+    withdrawal_storage_root: Bytes,
+    block_hash: Bytes,
+}
+
+impl L2OutputData {
+    /// The version 0 output root preimage of the L2 block
+    fn preimage(&self) -> OutputRootPreimage {
+        OutputRootPreimage::v0(
+            format!("{:#x}", self.state_root),
+            format!("{:#x}", self.withdrawal_storage_root),
+            format!("{:#x}", self.block_hash),
+        )
+    }
+}
+
+/// A function that fetches the data the output root of an L2 block is computed from.
+/// Parameters:
+/// * l2_rpc_fetcher: The L2 RPC fetcher
+/// * l2_block_number: The L2 block number
+///
+/// Returns:
+/// * The L2 output data, None if the L2 RPC does not have the block, or a transient
+///   `IndexerError::Rpc` if the call failed, so that the batch is retried
+async fn fetch_l2_output_data(
+    l2_rpc_fetcher: &Fetcher,
+    l2_block_number: u64,
+) -> Result<Option<L2OutputData>, IndexerError> {
+    let l2_block_number_hex = format!("0x{:x}", l2_block_number);
+    let maybe_out = telemetry::observe_rpc(
+        "optimism_outputAtBlock",
+        l2_rpc_fetcher.fetch_optimism_output_at_block(&l2_block_number_hex),
+    )
+    .await
+    .map_err(|e| {
+        IndexerError::Rpc(format!(
+            "optimism_outputAtBlock failed for L2 block {}: {}",
+            l2_block_number, e
+        ))
+    })?;
+
+    maybe_out
+        .map(|out| {
+            Ok(L2OutputData {
+                state_root: parse_bytes("state_root", &out.state_root)?,
+                withdrawal_storage_root: parse_bytes(
+                    "withdrawal_storage_root",
+                    &out.withdrawal_storage_root,
+                )?,
+                block_hash: parse_bytes("block_hash", &out.block_ref.hash)?,
+            })
+        })
+        .transpose()
+}
+
+/// A function that checks the root claims of the games indexed before claims were
+/// checked at index time.
+/// Parameters:
+/// * table_name: The name of the postgres table
+/// * client: The postgres client
+///
+/// Returns:
+/// * The number of games updated
+pub async fn backfill_claim_verified(
+    table_name: &str,
+    client: &tokio_postgres::Client,
+) -> Result<u64, IndexerError> {
+    let select_query = format!(
+        "SELECT id, root_claim, l2_state_root, l2_withdrawal_storage_root, l2_block_hash
+            FROM {}
+            WHERE claim_verified IS NULL
+            AND l2_state_root IS NOT NULL
+            AND l2_withdrawal_storage_root IS NOT NULL
+            AND l2_block_hash IS NOT NULL",
+        table_name
+    );
+    let update_query = format!(
+        "UPDATE {} SET claim_verified = $2 WHERE id = $1",
+        table_name
+    );
+
+    let rows = client.query(&select_query, &[]).await?;
+    let mut updated = 0;
+    for row in rows.iter() {
+        let id: i32 = row.get("id");
+        let root_claim: &str = row.get("root_claim");
+        let preimage = OutputRootPreimage::v0(
+            row.get("l2_state_root"),
+            row.get("l2_withdrawal_storage_root"),
+            row.get("l2_block_hash"),
+        );
+        let claim_verified = preimage.verify(root_claim);
+        updated += client
+            .execute(&update_query, &[&id, &claim_verified])
+            .await?;
+    }
+
+    Ok(updated)
+}
+
+/// A function that fetches the output root preimages of the outputs indexed before
+/// preimages were stored, in batches of ids so that it can stop at any time.
+/// Outputs whose L2 block the RPC does not have, or fails to serve, are skipped until the
+/// next start.
+/// Parameters:
+/// * table_name: The name of the postgres table
+/// * client: The postgres client
+/// * l2_rpc_fetcher: The L2 RPC fetcher
+/// * shutdown: Stops the backfill between two outputs
+///
+/// Returns:
+/// * The number of outputs updated
+pub async fn backfill_output_preimages(
+    table_name: &str,
+    client: &tokio_postgres::Client,
+    l2_rpc_fetcher: &Fetcher,
+    shutdown: &Shutdown,
+) -> Result<u64, IndexerError> {
+    let select_query = format!(
+        "SELECT id, l2_output_root, l2_block_number
+            FROM {}
+            WHERE l2_state_root IS NULL AND id > $1
+            ORDER BY id ASC
+            LIMIT 100",
+        table_name
+    );
+    let update_query = format!(
+        "UPDATE {} SET l2_state_root = $2, l2_withdrawal_storage_root = $3, l2_block_hash = $4 WHERE id = $1",
+        table_name
+    );
+
+    let mut last_id = 0i32;
+    let mut updated = 0;
+    loop {
+        let rows = client.query(&select_query, &[&last_id]).await?;
+        if rows.is_empty() {
+            return Ok(updated);
+        }
+        for row in rows.iter() {
+            if shutdown.is_requested() {
+                return Ok(updated);
+            }
+            last_id = row.get("id");
+            let l2_block_number: i32 = row.get("l2_block_number");
+            let l2_output_data =
+                match fetch_l2_output_data(l2_rpc_fetcher, l2_block_number as u64).await {
+                    Ok(Some(l2_output_data)) => l2_output_data,
+                    Ok(None) => continue,
+                    Err(e) if e.is_transient() => {
+                        warn!(l2_block_number, error = %e, "Output preimage not fetched");
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                };
+
+            let l2_output_root: &str = row.get("l2_output_root");
+            let preimage = l2_output_data.preimage();
+            if !preimage.verify(l2_output_root) {
+                warn!(
+                    l2_output_root,
+                    l2_block_number, "Output root does not match its preimage"
+                );
+            }
+            updated += client
+                .execute(
+                    &update_query,
+                    &[
+                        &last_id,
+                        &preimage.state_root,
+                        &preimage.message_passer_storage_root,
+                        &preimage.block_hash,
+                    ],
+                )
+                .await?;
+        }
+    }
+}
+
+/// A function that sets the L1 timestamp of the games indexed before it was read from
+/// their L1 block, which were stored with 0. Every L1 block is fetched once, in order
+/// of block hash so that it can stop at any time and resume on the next start.
+/// Parameters:
+/// * table_name: The name of the postgres table
+/// * client: The postgres client
+/// * l1_provider: The L1 provider
+/// * shutdown: Stops the backfill between two blocks
+///
+/// Returns:
+/// * The number of games updated
+pub async fn backfill_game_l1_timestamps(
+    table_name: &str,
+    client: &tokio_postgres::Client,
+    l1_provider: &Provider<Http>,
+    shutdown: &Shutdown,
+) -> Result<u64, IndexerError> {
+    let select_query = format!(
+        "SELECT DISTINCT l1_block_hash
+            FROM {}
+            WHERE l1_timestamp = 0 AND l1_block_hash > $1
+            ORDER BY l1_block_hash ASC
+            LIMIT 100",
+        table_name
+    );
+    let update_query = format!(
+        "UPDATE {} SET l1_timestamp = $2 WHERE l1_block_hash = $1 AND l1_timestamp = 0",
+        table_name
+    );
+
+    let mut last_block_hash = String::new();
+    let mut updated = 0;
+    loop {
+        let rows = client.query(&select_query, &[&last_block_hash]).await?;
+        if rows.is_empty() {
+            return Ok(updated);
+        }
+        for row in rows.iter() {
+            if shutdown.is_requested() {
+                return Ok(updated);
+            }
+            last_block_hash = row.get("l1_block_hash");
+            let block_hash = H256::from_str(&last_block_hash).map_err(|_| {
+                IndexerError::Decode(format!("invalid l1_block_hash: {}", last_block_hash))
+            })?;
+            let Some(block) =
+                telemetry::observe_rpc("eth_getBlockByHash", l1_provider.get_block(block_hash))
+                    .await?
+            else {
+                // Reorged out since it was indexed, nothing to take the timestamp from
+                warn!(l1_block_hash = %last_block_hash, "L1 block of indexed games not found");
+                continue;
+            };
+            let l1_timestamp = block.timestamp.as_u64() as i64;
+            updated += client
+                .execute(&update_query, &[&last_block_hash, &l1_timestamp])
+                .await?;
+        }
+    }
+}
+
+/// A function that decodes an OutputProposed log and fetches the preimage of its output root.
+/// Parameters:
+/// * log: The OutputProposed log
+/// * l2_rpc_fetcher: The L2 RPC fetcher, the preimage is not stored without it
+///
+/// Returns:
+/// * The output to insert
+pub async fn handle_opstack_events(
+    log: &Log,
+    l2_rpc_fetcher: Option<&Fetcher>,
+) -> Result<OPStackParameters, IndexerError> {
+    let event: OutputProposedFilter = decode_log(log)?;
+    let meta = L1LogMeta::from_log(log)?;
+
+    let l2_output_root = Bytes::from(event.output_root.to_vec());
+    let l2_output_index = event.l_2_output_index;
+    let l2_block_number = event.l_2_block_number;
+    let l1_timestamp = event.l_1_timestamp;
+    let l1_transaction_hash = meta.transaction_hash;
+    let l1_block_number = meta.block_number;
+    let l1_transaction_index = meta.transaction_index;
+    let l1_block_hash = meta.block_hash;
+
+    info!(
+        %l2_output_root,
+        %l2_output_index,
+        %l2_block_number,
+        %l1_timestamp,
+        %l1_block_hash,
+        l1_transaction_index = l1_transaction_index.as_u64(),
+        "Decoded OutputProposed"
+    );
+
+    let l2_output_data = match (l2_rpc_fetcher, u64::try_from(l2_block_number)) {
+        (Some(fetcher), Ok(l2_block_number)) => {
+            fetch_l2_output_data(fetcher, l2_block_number).await?
+        }
+        _ => None,
+    };
+    if let Some(l2_output_data) = l2_output_data.as_ref() {
+        if !l2_output_data
+            .preimage()
+            .verify(&format!("{:#x}", l2_output_root))
+        {
+            warn!(
+                %l2_output_root,
+                %l2_block_number,
+                "Output root does not match its preimage"
+            );
+        }
+    }
+
+    Ok(OPStackParameters {
         l2_output_root,
         l2_output_index,
         l2_block_number,
@@ -337,49 +647,55 @@ pub fn handle_opstack_events(log: &Log) -> OPStackParameters {
         l1_block_number,
         l1_transaction_index,
         l1_block_hash,
-    }
+        l2_output_data,
+    })
 }
 
 pub async fn handle_opstack_fdg_events(
     log: &Log,
     network: &Network,
+    network_config: &NetworkConfig,
     l1_provider: Arc<Provider<Http>>,
+    l2_rpc_fetcher: &Fetcher,
     game_index: u64,
-) -> Result<OPStackDisputeGameParameters, eyre::Error> {
-    let dispute_proxy_address: Address = Address::from(log.topics[1]).into();
-    let game_type = {
-        let mut bytes = [0u8; 32];
-        bytes.copy_from_slice(log.topics[2].as_bytes());
-        U256::from_big_endian(&bytes).as_u32()
-    };
-    let root_claim = Bytes::from(log.topics[3].as_bytes().to_vec());
+) -> Result<OPStackDisputeGameParameters, IndexerError> {
+    let event: DisputeGameCreatedFilter = decode_log(log)?;
+    let meta = L1LogMeta::from_log(log)?;
+
+    let dispute_proxy_address: Address = event.dispute_proxy;
+    let game_type = event.game_type;
+    let root_claim = Bytes::from(event.root_claim.to_vec());
+
+    // DisputeGameCreated carries no data, so the L1 timestamp comes from the block itself
+    let l1_timestamp = telemetry::observe_rpc(
+        "eth_getBlockByHash",
+        l1_provider.get_block(H256::from_slice(&meta.block_hash)),
+    )
+    .await?
+    .ok_or_else(|| IndexerError::Rpc("L1 block of DisputeGameCreated log not found".to_string()))?
+    .timestamp;
+    let l1_timestamp = U64::from(l1_timestamp.as_u64());
 
     let dispute_game = DisputeGame::new(dispute_proxy_address, l1_provider.clone());
 
-    let status_u8: u8 = dispute_game.status().call().await.map_err(|e| {
-        eprintln!("status() failed: {e:?}");
-        eyre!("status() failed: {e:?}")
-    })?;
+    let status_u8: u8 = telemetry::observe_rpc("eth_call", dispute_game.status().call())
+        .await
+        .map_err(|e| IndexerError::from_contract_call("status()", e))?;
     let game_status: u64 = status_u8 as u64;
 
-    let timestamp: u64 = dispute_game.created_at().call().await.map_err(|e| {
-        eprintln!("created_at() failed: {e:?}");
-        eyre!("created_at() failed: {e:?}")
-    })?;
+    let timestamp: u64 = telemetry::observe_rpc("eth_call", dispute_game.created_at().call())
+        .await
+        .map_err(|e| IndexerError::from_contract_call("created_at()", e))?;
 
     // This is bacause OP Sepolia game contract dont have the gemaCreator method
-    let mut game_creator: Address =
-        Address::from_str("0x0000000000000000000000000000000000000000").unwrap();
+    let mut game_creator: Address = Address::zero();
 
     if network.chain_type == ChainType::Mainnet {
-        game_creator = dispute_game.game_creator().call().await.map_err(|e| {
-            eprintln!("game_creator() failed: {e:?}");
-            eyre!("game_creator() failed: {e:?}")
-        })?;
+        game_creator = telemetry::observe_rpc("eth_call", dispute_game.game_creator().call())
+            .await
+            .map_err(|e| IndexerError::from_contract_call("game_creator()", e))?;
     }
 
-    let network_config = get_network_config(network.chain_type, network.chain_name);
-
     let trusted_proposer: Option<Address> = network_config
         .trusted_proposer_address
         .as_deref()
@@ -390,33 +706,32 @@ pub async fn handle_opstack_fdg_events(
         None => false,
     };
 
-    println!(
-        "Fetched dispute game at address {:#x}",
-        dispute_proxy_address
+    info!(
+        game_address = format!("{:#x}", dispute_proxy_address),
+        game_status, "Fetched dispute game"
     );
 
-    if !(game_status == 2 || (is_trusted_proposer && (game_status == 0 || game_status == 2))) {
+    if game_status != 2 && !(is_trusted_proposer && game_status == 0) {
         // If the dispute game is not finalised, we anyway inserting it to the db with correct state
         // Later, in db retrieval state wi checking this condition also
-        println!(
-           "Dispute game not finalized (game_status != 2 and not trusted proposer with game_status 0 or 2)"
+        debug!(
+            "Dispute game not finalized (game_status != 2 and not trusted proposer with game_status 0 or 2)"
         );
         //return Err(eyre::eyre!("Dispute game not finalized (status != 2 and not trusted proposer with status 0 or 2)"));
     }
 
-    let l2_block_number: U256 = dispute_game.l_2_block_number().call().await.map_err(|e| {
-        eprintln!("l2_block_number() failed: {e:?}");
-        eyre!("l2_block_number() failed: {e:?}")
-    })?;
+    let l2_block_number: U256 =
+        telemetry::observe_rpc("eth_call", dispute_game.l_2_block_number().call())
+            .await
+            .map_err(|e| IndexerError::from_contract_call("l2_block_number()", e))?;
 
     // Check if L2 block number is within u64 range before proceeding
     let l2_block_number_u64: u64 = match l2_block_number.try_into() {
         Ok(num) => num,
         Err(_) => {
-            eprintln!(
-                "L2 block number {} is too large for u64 (max u64: {}), skipping L2 data fetch",
-                l2_block_number,
-                u64::MAX
+            warn!(
+                %l2_block_number,
+                "L2 block number is too large for u64, skipping L2 data fetch"
             );
             // Return early with None values for L2 data
             return Ok(OPStackDisputeGameParameters {
@@ -429,107 +744,43 @@ pub async fn handle_opstack_fdg_events(
                 proposer_address: game_creator,
                 l2_block_number,
                 l2_block_number_safe: None, // No safe version available
-                l2_state_root: None,
-                l2_withdrawal_storage_root: None,
-                l2_block_hash: None,
-                l1_timestamp: U64::from_big_endian(&log.data[..]),
-                l1_transaction_hash: Bytes::from(log.transaction_hash.unwrap().as_bytes().to_vec()),
-                l1_block_number: log.block_number.unwrap(),
-                l1_transaction_index: log.transaction_index.unwrap(),
-                l1_block_hash: Bytes::from(log.block_hash.unwrap().as_bytes().to_vec()),
+                l2_output_data: None,
+                claim_verified: None,
+                l1_timestamp,
+                l1_transaction_hash: meta.transaction_hash,
+                l1_block_number: meta.block_number,
+                l1_transaction_index: meta.transaction_index,
+                l1_block_hash: meta.block_hash,
             });
         }
     };
 
     // Get the L2 block details from L2 RPC
-    let l2_rpc_url = std::env::var("L2_RPC_URL").expect("L2_RPC_URL must be set.");
-
-    let l2_rpc_fetcher = Fetcher::new(l2_rpc_url.to_string());
-
-    let l2_block_number_hex = format!("0x{:x}", l2_block_number_u64);
-    //let optimism_output = l2_rpc_fetcher.fetch_optimism_output_at_block(&l2_block_number_hex).await.unwrap();
-    // let optimism_output = match l2_rpc_fetcher.fetch_optimism_output_at_block(&l2_block_number_hex).await? {
-    //     Some(out) => { /* process */ }
-    //     None => {
-    //         // block/output not available → skip insert or insert NULL/defaults
-    //     }
-    // };
-
-    // let l2_state_root: Bytes =
-    //     Bytes::from_str(&optimism_output.state_root).expect("Invalid state_root hex");
-
-    // let l2_withdrawal_storage_root: Bytes =
-    //     Bytes::from_str(&optimism_output.withdrawal_storage_root)
-    //         .expect("Invalid withdrawal_storage_root hex");
-
-    // let l2_block_hash: Bytes =
-    //     Bytes::from_str(&optimism_output.block_ref.hash).expect("Invalid block hash hex");
-    //
-
-    let mut maybe_out = None;
-
-    if network.chain_name == ChainName::Base
+    let l2_output_data = if network.chain_name == ChainName::Base
         && network.chain_type == ChainType::Sepolia
         && l2_block_number_u64 < 25000000
     {
-    } else {
         // Until we find RPC that supports older blocks
-        maybe_out = match l2_rpc_fetcher
-            .fetch_optimism_output_at_block(&l2_block_number_hex)
-            .await
-        {
-            Ok(val) => val,
-
-            Err(e) => {
-                eprintln!(
-                    "fetch_optimism_output_at_block({}) failed: {:?}",
-                    l2_block_number_hex, e
-                );
-
-                // Instead of failing completely, continue with None values for L2 data
-
-                // This allows the dispute game to be indexed even if L2 data is unavailable
-
-                None
-            }
-        };
-    }
-
-    // let maybe_out = l2_rpc_fetcher
-    //     .fetch_optimism_output_at_block(&l2_block_number_hex)
-    //     .await?;
-
-    let (l2_state_root, l2_withdrawal_storage_root, l2_block_hash) = match maybe_out {
-        Some(out) => (
-            Some(
-                parse_bytes("state_root", &out.state_root)
-                    .map_err(|e| eyre!("Failed to parse state_root: {}", e))?,
-            ),
-            Some(
-                parse_bytes("withdrawal_storage_root", &out.withdrawal_storage_root)
-                    .map_err(|e| eyre!("Failed to parse withdrawal_storage_root: {}", e))?,
-            ),
-            Some(
-                parse_bytes("block_hash", &out.block_ref.hash)
-                    .map_err(|e| eyre!("Failed to parse block_hash: {}", e))?,
-            ),
-        ),
-        None => (None, None, None),
+        None
+    } else {
+        // A game is indexed even if the L2 RPC does not have its block
+        fetch_l2_output_data(l2_rpc_fetcher, l2_block_number_u64).await?
     };
 
-    // Example insert (VARCHAR columns; store hex strings). Assumes columns are NULLable.
-    // let l2_state_root_hex: Option<String> =
-    //     l2_state_root.as_ref().map(|b| format!("0x{}", hex::encode(b)));
-    // let l2_withdrawal_storage_root_hex: Option<String> =
-    //     l2_withdrawal_storage_root.as_ref().map(|b| format!("0x{}", hex::encode(b)));
-    // let l2_block_hash_hex: Option<String> =
-    //     l2_block_hash.as_ref().map(|b| format!("0x{}", hex::encode(b)));
+    let claim_verified = l2_output_data
+        .as_ref()
+        .map(|data| data.preimage().verify(&format!("{:#x}", root_claim)));
+    if claim_verified == Some(false) {
+        warn!(
+            l2_block_number = l2_block_number_u64,
+            "Root claim of dispute game does not match the output root of its L2 block"
+        );
+    }
 
-    let l1_timestamp = U64::from_big_endian(&log.data[..]);
-    let l1_transaction_hash = Bytes::from(log.transaction_hash.unwrap().as_bytes().to_vec());
-    let l1_block_number = log.block_number.unwrap();
-    let l1_transaction_index = log.transaction_index.unwrap();
-    let l1_block_hash = Bytes::from(log.block_hash.unwrap().as_bytes().to_vec());
+    let l1_transaction_hash = meta.transaction_hash;
+    let l1_block_number = meta.block_number;
+    let l1_transaction_index = meta.transaction_index;
+    let l1_block_hash = meta.block_hash;
 
     Ok(OPStackDisputeGameParameters {
         game_index,
@@ -541,9 +792,8 @@ pub async fn handle_opstack_fdg_events(
         proposer_address: game_creator,
         l2_block_number,
         l2_block_number_safe: Some(l2_block_number_u64), // Use the safe u64 version
-        l2_state_root,
-        l2_withdrawal_storage_root,
-        l2_block_hash,
+        l2_output_data,
+        claim_verified,
         l1_timestamp,
         l1_transaction_hash,
         l1_block_number,
@@ -0,0 +1,56 @@
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+/// A cooperative shutdown flag set once SIGINT or SIGTERM is received
+#[derive(Clone)]
+pub struct Shutdown(watch::Receiver<bool>);
+
+impl Shutdown {
+    /// Spawns a task that listens for SIGINT/SIGTERM and flips the returned flag
+    pub fn listen_for_signals() -> Self {
+        let (tx, rx) = watch::channel(false);
+        tokio::spawn(async move {
+            wait_for_signal().await;
+            info!("Shutdown signal received, stopping after the in-flight batch");
+            let _ = tx.send(true);
+        });
+        Self(rx)
+    }
+
+    pub fn is_requested(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Resolves once shutdown has been requested, immediately if it already was
+    pub async fn requested(&mut self) {
+        while !self.is_requested() {
+            if self.0.changed().await.is_err() {
+                // The signal listener is gone, no shutdown will ever be requested
+                std::future::pending::<()>().await;
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    match signal(SignalKind::terminate()) {
+        Ok(mut sigterm) => {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = sigterm.recv() => {}
+            }
+        }
+        Err(e) => {
+            warn!(error = %e, "Failed to listen for SIGTERM, only SIGINT will stop the indexer");
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() {
+    let _ = tokio::signal::ctrl_c().await;
+}
@@ -0,0 +1,141 @@
+use tokio_postgres::GenericClient;
+
+use crate::indexer::StreamKind;
+
+/// A function that marks a stream as running when the indexer starts.
+/// The last error is kept so it stays visible until the stream makes progress again.
+/// Parameters:
+/// * client: The postgres client
+/// * network: The network name, e.g. optimism_mainnet
+/// * stream: The stream that starts
+/// * l1_block_cap: The last L1 block the stream may index, if any
+///
+/// Returns:
+/// Returns nothing except for error
+pub async fn mark_stream_started(
+    client: &tokio_postgres::Client,
+    network: &str,
+    stream: StreamKind,
+    l1_block_cap: Option<u64>,
+) -> Result<(), tokio_postgres::Error> {
+    let l1_block_cap = l1_block_cap.map(|block| block as i64);
+    client
+        .execute(
+            "INSERT INTO indexer_status (network, stream, halted, l1_block_cap, updated_at)
+             VALUES ($1, $2, FALSE, $3, NOW())
+             ON CONFLICT (network, stream)
+             DO UPDATE SET halted = FALSE, l1_block_cap = $3, updated_at = NOW()",
+            &[&network, &stream.as_str(), &l1_block_cap],
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// How far a stream got, written with the batch that made the progress
+pub struct StreamProgress {
+    /// The last L1 block indexed
+    pub l1_block: u64,
+    /// The timestamp of `l1_block`
+    pub l1_timestamp: u64,
+    /// The L1 timestamp of the last event of the batch, if it had any
+    pub last_event_l1_timestamp: Option<u64>,
+}
+
+/// A function that persists the progress of a stream and clears its last error.
+/// Called in the transaction of the batch, so the progress matches what is stored.
+/// Parameters:
+/// * client: The postgres client or transaction
+/// * network: The network name, e.g. optimism_mainnet
+/// * stream: The stream that made progress
+/// * progress: The progress of the stream
+///
+/// Returns:
+/// Returns nothing except for error
+pub async fn record_stream_progress(
+    client: &impl GenericClient,
+    network: &str,
+    stream: StreamKind,
+    progress: &StreamProgress,
+) -> Result<(), tokio_postgres::Error> {
+    let l1_block = progress.l1_block as i64;
+    let l1_timestamp = progress.l1_timestamp as i64;
+    let last_event_l1_timestamp = progress.last_event_l1_timestamp.map(|ts| ts as i64);
+    client
+        .execute(
+            "INSERT INTO indexer_status (network, stream, last_processed_l1_block, last_processed_l1_timestamp, last_event_l1_timestamp, updated_at)
+             VALUES ($1, $2, $3, $4, $5, NOW())
+             ON CONFLICT (network, stream)
+             DO UPDATE SET last_processed_l1_block = $3, last_processed_l1_timestamp = $4,
+                last_event_l1_timestamp = COALESCE($5, indexer_status.last_event_l1_timestamp),
+                last_error = NULL, last_error_at = NULL, updated_at = NOW()",
+            &[
+                &network,
+                &stream.as_str(),
+                &l1_block,
+                &l1_timestamp,
+                &last_event_l1_timestamp,
+            ],
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// A function that persists the current L1 head for every stream of a network,
+/// so their lag can be computed without an L1 RPC.
+/// Parameters:
+/// * client: The postgres client
+/// * network: The network name, e.g. optimism_mainnet
+/// * head_block: The latest L1 block
+/// * head_timestamp: The timestamp of `head_block`
+///
+/// Returns:
+/// Returns nothing except for error
+pub async fn record_l1_head(
+    client: &tokio_postgres::Client,
+    network: &str,
+    head_block: u64,
+    head_timestamp: u64,
+) -> Result<(), tokio_postgres::Error> {
+    client
+        .execute(
+            "UPDATE indexer_status
+             SET l1_head_block = $2, l1_head_timestamp = $3, updated_at = NOW()
+             WHERE network = $1",
+            &[&network, &(head_block as i64), &(head_timestamp as i64)],
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// A function that persists the error that made a stream fail.
+/// Parameters:
+/// * client: The postgres client
+/// * network: The network name, e.g. optimism_mainnet
+/// * stream: The stream that failed
+/// * error: The error message
+/// * halted: Whether the stream stopped because of this error
+///
+/// Returns:
+/// Returns nothing except for error
+pub async fn record_stream_error(
+    client: &tokio_postgres::Client,
+    network: &str,
+    stream: StreamKind,
+    error: &str,
+    halted: bool,
+) -> Result<(), tokio_postgres::Error> {
+    client
+        .execute(
+            "INSERT INTO indexer_status (network, stream, halted, last_error, last_error_at, updated_at)
+             VALUES ($1, $2, $3, $4, NOW(), NOW())
+             ON CONFLICT (network, stream)
+             DO UPDATE SET halted = $3, last_error = $4, last_error_at = NOW(), updated_at = NOW()",
+            &[&network, &stream.as_str(), &halted, &error],
+        )
+        .await?;
+
+    Ok(())
+}
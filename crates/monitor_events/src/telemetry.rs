@@ -0,0 +1,136 @@
+use std::future::Future;
+use std::net::SocketAddr;
+use std::time::Instant;
+
+use ::metrics::{counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram};
+use eyre::{Result, WrapErr};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder};
+use tracing::{field, info, info_span, Instrument};
+
+/// Buckets of the latency histograms, in seconds
+const DURATION_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+/// Buckets of the number of logs fetched per batch
+const BATCH_LOGS_BUCKETS: &[f64] = &[0.0, 1.0, 5.0, 10.0, 50.0, 100.0, 500.0, 1000.0, 5000.0];
+
+/// A function that serves the Prometheus metrics of the indexer on `METRICS_ADDR`,
+/// e.g. `0.0.0.0:9100`. Without it metrics are not recorded at all, so several
+/// indexers can share a host without fighting over a port.
+///
+/// Returns:
+/// Returns nothing except for error
+pub fn install_from_env() -> Result<()> {
+    let addr = match std::env::var("METRICS_ADDR") {
+        Ok(addr) if !addr.is_empty() => addr,
+        _ => return Ok(()),
+    };
+    let addr: SocketAddr = addr.parse().wrap_err("Invalid METRICS_ADDR")?;
+
+    PrometheusBuilder::new()
+        .with_http_listener(addr)
+        .set_buckets(DURATION_BUCKETS)?
+        .set_buckets_for_metric(
+            Matcher::Full("indexer_batch_logs".to_string()),
+            BATCH_LOGS_BUCKETS,
+        )?
+        .install()
+        .wrap_err("Failed to start the metrics listener")?;
+
+    describe_gauge!(
+        "indexer_lag_blocks",
+        "L1 blocks between the L1 head and the last block indexed by a stream"
+    );
+    describe_gauge!(
+        "indexer_lag_seconds",
+        "Seconds between the L1 head and the last block indexed by a stream"
+    );
+    describe_histogram!("indexer_batch_logs", "Logs fetched per batch");
+    describe_counter!("indexer_logs_total", "Logs indexed");
+    describe_histogram!(
+        "indexer_rpc_request_duration_seconds",
+        "Latency of the L1 and L2 RPC requests by method"
+    );
+    describe_counter!("indexer_rpc_errors_total", "Failed RPC requests by method");
+    describe_histogram!(
+        "indexer_db_write_duration_seconds",
+        "Time to insert and commit the rows of a batch"
+    );
+    describe_gauge!("indexer_dispute_games", "Indexed dispute games by status");
+
+    info!(%addr, "Serving Prometheus metrics");
+    Ok(())
+}
+
+/// A function that times an RPC request, counts its failures and traces it under an
+/// `rpc` span.
+/// Parameters:
+/// * method: The RPC method, e.g. eth_getLogs
+/// * request: The request to run
+///
+/// Returns:
+/// * The result of the request
+pub async fn observe_rpc<T, E>(
+    method: &'static str,
+    request: impl Future<Output = Result<T, E>>,
+) -> Result<T, E> {
+    let span = info_span!(
+        "rpc",
+        otel.kind = "client",
+        rpc.system = "jsonrpc",
+        rpc.method = method,
+        otel.status_code = field::Empty
+    );
+    let started = Instant::now();
+    let result = request.instrument(span.clone()).await;
+    histogram!("indexer_rpc_request_duration_seconds", "method" => method)
+        .record(started.elapsed().as_secs_f64());
+    if result.is_err() {
+        span.record("otel.status_code", "ERROR");
+        counter!("indexer_rpc_errors_total", "method" => method).increment(1);
+    }
+    result
+}
+
+pub fn record_batch_logs(network: &str, stream: &'static str, logs: usize) {
+    let labels = [
+        ("network", network.to_string()),
+        ("stream", stream.to_string()),
+    ];
+    histogram!("indexer_batch_logs", &labels).record(logs as f64);
+    counter!("indexer_logs_total", &labels).increment(logs as u64);
+}
+
+pub fn record_db_write(network: &str, stream: &'static str, started: Instant) {
+    histogram!(
+        "indexer_db_write_duration_seconds",
+        "network" => network.to_string(),
+        "stream" => stream
+    )
+    .record(started.elapsed().as_secs_f64());
+}
+
+pub fn record_lag(network: &str, stream: &'static str, lag_blocks: u64, lag_seconds: Option<u64>) {
+    let labels = [
+        ("network", network.to_string()),
+        ("stream", stream.to_string()),
+    ];
+    gauge!("indexer_lag_blocks", &labels).set(lag_blocks as f64);
+    if let Some(lag_seconds) = lag_seconds {
+        gauge!("indexer_lag_seconds", &labels).set(lag_seconds as f64);
+    }
+}
+
+/// A function that publishes the number of indexed games per `game_state`
+pub fn record_dispute_games(network: &str, games_by_state: &[(i64, i64)]) {
+    for (game_state, games) in games_by_state {
+        let status = match game_state {
+            0 => "in_progress",
+            1 => "challenger_wins",
+            2 => "defender_wins",
+            _ => "unknown",
+        };
+        gauge!("indexer_dispute_games", "network" => network.to_string(), "status" => status)
+            .set(*games as f64);
+    }
+}
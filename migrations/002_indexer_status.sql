@@ -0,0 +1,14 @@
+-- Migration: 002_indexer_status.sql
+-- Description: Per network and stream health of the indexer
+-- Each monitor-events process keeps one row per stream (outputs, dispute_games, arbitrum).
+-- A stream that hits a permanent error is halted and its error is kept here.
+
+CREATE TABLE IF NOT EXISTS indexer_status (
+    network                 VARCHAR NOT NULL,
+    stream                  VARCHAR NOT NULL,
+    halted                  BOOLEAN NOT NULL DEFAULT FALSE,
+    last_error              VARCHAR,
+    last_error_at           TIMESTAMPTZ,
+    updated_at              TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (network, stream)
+);
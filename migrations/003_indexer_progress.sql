@@ -0,0 +1,12 @@
+-- Migration: 003_indexer_progress.sql
+-- Description: How far each indexer stream got on L1, served by /v1/status
+-- The progress columns are written after every committed batch. l1_head_* is the L1 head
+-- the stream is compared to, and l1_block_cap the last L1 block it may index, if any.
+
+ALTER TABLE indexer_status
+    ADD COLUMN IF NOT EXISTS last_processed_l1_block     BIGINT,
+    ADD COLUMN IF NOT EXISTS last_processed_l1_timestamp BIGINT,
+    ADD COLUMN IF NOT EXISTS last_event_l1_timestamp     BIGINT,
+    ADD COLUMN IF NOT EXISTS l1_head_block               BIGINT,
+    ADD COLUMN IF NOT EXISTS l1_head_timestamp           BIGINT,
+    ADD COLUMN IF NOT EXISTS l1_block_cap                BIGINT;
@@ -0,0 +1,45 @@
+-- Migration: 004_output_preimages.sql
+-- Description: Output root preimage of the legacy OPStack outputs
+-- The state root, the L2ToL1MessagePasser storage root and the block hash of the L2 block
+-- hash to the output root. They are null until the indexer fetched them from the L2 RPC.
+-- The fault dispute games tables already have these columns.
+
+ALTER TABLE optimism_mainnet
+    ADD COLUMN IF NOT EXISTS l2_state_root              VARCHAR,
+    ADD COLUMN IF NOT EXISTS l2_withdrawal_storage_root VARCHAR,
+    ADD COLUMN IF NOT EXISTS l2_block_hash              VARCHAR;
+
+ALTER TABLE optimism_sepolia
+    ADD COLUMN IF NOT EXISTS l2_state_root              VARCHAR,
+    ADD COLUMN IF NOT EXISTS l2_withdrawal_storage_root VARCHAR,
+    ADD COLUMN IF NOT EXISTS l2_block_hash              VARCHAR;
+
+ALTER TABLE base_mainnet
+    ADD COLUMN IF NOT EXISTS l2_state_root              VARCHAR,
+    ADD COLUMN IF NOT EXISTS l2_withdrawal_storage_root VARCHAR,
+    ADD COLUMN IF NOT EXISTS l2_block_hash              VARCHAR;
+
+ALTER TABLE base_sepolia
+    ADD COLUMN IF NOT EXISTS l2_state_root              VARCHAR,
+    ADD COLUMN IF NOT EXISTS l2_withdrawal_storage_root VARCHAR,
+    ADD COLUMN IF NOT EXISTS l2_block_hash              VARCHAR;
+
+ALTER TABLE zora_mainnet
+    ADD COLUMN IF NOT EXISTS l2_state_root              VARCHAR,
+    ADD COLUMN IF NOT EXISTS l2_withdrawal_storage_root VARCHAR,
+    ADD COLUMN IF NOT EXISTS l2_block_hash              VARCHAR;
+
+ALTER TABLE zora_sepolia
+    ADD COLUMN IF NOT EXISTS l2_state_root              VARCHAR,
+    ADD COLUMN IF NOT EXISTS l2_withdrawal_storage_root VARCHAR,
+    ADD COLUMN IF NOT EXISTS l2_block_hash              VARCHAR;
+
+ALTER TABLE world_chain_mainnet
+    ADD COLUMN IF NOT EXISTS l2_state_root              VARCHAR,
+    ADD COLUMN IF NOT EXISTS l2_withdrawal_storage_root VARCHAR,
+    ADD COLUMN IF NOT EXISTS l2_block_hash              VARCHAR;
+
+ALTER TABLE world_chain_sepolia
+    ADD COLUMN IF NOT EXISTS l2_state_root              VARCHAR,
+    ADD COLUMN IF NOT EXISTS l2_withdrawal_storage_root VARCHAR,
+    ADD COLUMN IF NOT EXISTS l2_block_hash              VARCHAR;
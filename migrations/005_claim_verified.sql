@@ -0,0 +1,12 @@
+-- Migration: 005_claim_verified.sql
+-- Description: Whether the root claim of a dispute game matches the output root of its L2 block
+-- Null when the L2 data needed to check the claim is missing. The indexer fills it in on
+-- start for the games indexed before the check existed.
+
+ALTER TABLE optimism_mainnet_fault_dispute_games ADD COLUMN IF NOT EXISTS claim_verified BOOLEAN;
+
+ALTER TABLE optimism_sepolia_fault_dispute_games ADD COLUMN IF NOT EXISTS claim_verified BOOLEAN;
+
+ALTER TABLE base_mainnet_fault_dispute_games ADD COLUMN IF NOT EXISTS claim_verified BOOLEAN;
+
+ALTER TABLE base_sepolia_fault_dispute_games ADD COLUMN IF NOT EXISTS claim_verified BOOLEAN;
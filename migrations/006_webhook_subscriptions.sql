@@ -0,0 +1,17 @@
+-- Migration: 006_webhook_subscriptions.sql
+-- Description: The webhooks registered through POST /v1/webhooks
+-- The secret signs the deliveries and is only returned when the webhook is created. The
+-- last_* columns and consecutive_failures track the outcome of the latest delivery.
+
+CREATE TABLE IF NOT EXISTS webhook_subscriptions (
+    id                      VARCHAR PRIMARY KEY,
+    url                     VARCHAR NOT NULL,
+    networks                VARCHAR[] NOT NULL,
+    events                  VARCHAR[] NOT NULL,
+    secret                  VARCHAR NOT NULL,
+    created_at              TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_delivery_at        TIMESTAMPTZ,
+    last_status             INTEGER,
+    last_error              VARCHAR,
+    consecutive_failures    INTEGER NOT NULL DEFAULT 0
+);
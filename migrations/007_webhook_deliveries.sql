@@ -0,0 +1,25 @@
+-- Migration: 007_webhook_deliveries.sql
+-- Description: The state the webhook events are detected from, and the deliveries to send
+-- webhook_networks keeps what was last seen of each subscribed network, its row is locked
+-- while the new events are queued so that replicas do not queue them twice.
+-- webhook_deliveries is the outbox: each replica claims the due rows with
+-- FOR UPDATE SKIP LOCKED, and a row is deleted once delivered or given up on.
+
+CREATE TABLE IF NOT EXISTS webhook_networks (
+    network                 VARCHAR PRIMARY KEY,
+    highest_l2_block        BIGINT,
+    trusted_games           BIGINT[],
+    updated_at              TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS webhook_deliveries (
+    subscription_id         VARCHAR NOT NULL REFERENCES webhook_subscriptions (id) ON DELETE CASCADE,
+    event_id                VARCHAR NOT NULL,
+    body                    VARCHAR NOT NULL,
+    attempts                INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at         TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at              TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (subscription_id, event_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_next_attempt_at ON webhook_deliveries(next_attempt_at);
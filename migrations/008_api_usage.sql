@@ -0,0 +1,12 @@
+-- Migration: 008_api_usage.sql
+-- Description: What each API client used of its rate limit and daily quota
+-- One row per client name of API_KEYS_FILE, shared by every replica of the micro-service.
+-- The row is locked while a request is counted, and only clients with limits get one.
+
+CREATE TABLE IF NOT EXISTS api_usage (
+    client                  VARCHAR PRIMARY KEY,
+    tokens                  DOUBLE PRECISION NOT NULL,
+    refilled_at             TIMESTAMPTZ NOT NULL,
+    day                     BIGINT NOT NULL DEFAULT 0,
+    requests_today          BIGINT NOT NULL DEFAULT 0
+);